@@ -0,0 +1,81 @@
+//! Times reading a large generated source file, to keep an eye on the speed
+//! of the lexer and the parser. Run it with `cargo bench --bench lexer`.
+//!
+//! Every form is quoted, so evaluating the file costs next to nothing next to
+//! reading it, and only uses syntax older versions of risp understand: set
+//! RISP_BENCH_BASELINE to the path of another risp binary to compare the two.
+//! That binary must be inside a checkout that has a stdlib/ directory, as it
+//! is run from there to find its standard library.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const FORMS: usize = 200_000;
+const RUNS: usize = 5;
+
+fn source() -> String {
+    let mut src = String::new();
+    for i in 0..FORMS {
+        src.push_str(&format!(
+            ";; form {}\n(quote (item-{} {} {}.25 \"text with \\\"quotes\\\"\\n\" (nested list) [1 2 3]))\n",
+            i, i, i, i
+        ));
+    }
+    src
+}
+
+// the checkout a risp binary was built in, where older versions look for
+// their standard library
+fn checkout(bin: &Path) -> &Path {
+    bin.ancestors()
+        .find(|d| d.join("stdlib").is_dir())
+        .unwrap_or(Path::new(env!("CARGO_MANIFEST_DIR")))
+}
+
+// the median time risp at bin takes to load path
+fn time(bin: &Path, path: &Path) -> Duration {
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let out = Command::new(bin)
+                .arg(path)
+                .current_dir(checkout(bin))
+                .output()
+                .expect("could not run risp");
+            let elapsed = start.elapsed();
+            // the forms are quoted, anything printed is an error
+            assert!(
+                out.status.success() && out.stdout.is_empty() && out.stderr.is_empty(),
+                "{} failed: {}{}",
+                bin.display(),
+                String::from_utf8_lossy(&out.stdout),
+                String::from_utf8_lossy(&out.stderr)
+            );
+            elapsed
+        })
+        .collect();
+    times.sort();
+    times[RUNS / 2]
+}
+
+fn main() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("target");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("bench-lexer.risp");
+    fs::write(&path, source()).unwrap();
+    let mut bins = vec![PathBuf::from(env!("CARGO_BIN_EXE_risp"))];
+    bins.extend(env::var_os("RISP_BENCH_BASELINE").map(PathBuf::from));
+    for bin in bins {
+        let t = time(&bin, &path);
+        println!(
+            "{}: read {} forms in {:.2}s (median of {})",
+            bin.display(),
+            FORMS,
+            t.as_secs_f64(),
+            RUNS
+        );
+    }
+}
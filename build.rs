@@ -10,11 +10,11 @@ const REPL0: &str = "
 ██╔══██╗██║╚════██║██╔═══╝
 ██║  ██║██║███████║██║ REPL
 ╚═╝  ╚═╝╚═╝╚══════╝╚═╝ ";
-const REPL1: &str = "Use Ctrl-C or Ctrl-D to exit REPL. Type (help) for help.";
+const REPL1: &str = "Use Ctrl-C or Ctrl-D to exit REPL. Type (help) or :help for help.";
 
 fn main() -> std::io::Result<()> {
     let output = Command::new("git")
-        .args(&["rev-parse", "--short", "HEAD"])
+        .args(["rev-parse", "--short", "HEAD"])
         .output()?;
     if output.status.success() {
         let mut res = OpenOptions::new()
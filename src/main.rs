@@ -1,94 +1,297 @@
 use std::env;
-
-#[macro_use]
-extern crate lazy_static;
-extern crate rustyline;
-use rustyline::error::ReadlineError;
-use rustyline::Editor;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::process;
 
 #[macro_use]
 mod risp;
-use risp::{rep, REnv, RVal, RVal::*};
+mod repl;
+mod runner;
+use risp::{
+    find_manifest, format_source, pprint, print_width, read_project, strip_shebang, Project, REnv,
+    RErr, RStr, RVal, RVal::*, MANIFEST,
+};
 
 const REPL0: &str = include_str!("../.repl_logo");
-const REPL1: &str = "# ";
+
+const USAGE: &str = "usage: risp [options] [file | -] [args ...]
+       risp run [options] [args ...]
+       risp fmt [--check] [file ...]
+       risp test [path ...]
+
+options:
+  -e expr          evaluate expr and print its value, can be repeated
+  -i               start the REPL after running the program
+  --load file      load file before the program, can be repeated
+  --no-prelude     do not load the standard library
+  --debug          pause before the first function call
+  --trace          log every function call to stderr
+  --profile[=path] report the time spent in each function to stderr
+  -h, --help       show this message
+
+a file of - reads the program from stdin, and the arguments after the
+program are bound to *args* as a vector of strings
+
+risp run runs the entry point of the project whose risp.toml is in the
+current directory or one of its parents, and risp test without paths runs
+the tests under its test and source directories";
+
+#[derive(Default)]
+struct Options {
+    exprs: Vec<String>,
+    interactive: bool,
+    loads: Vec<String>,
+    no_prelude: bool,
+    debug: bool,
+    trace: bool,
+    // --profile=path also writes the folded stacks to path
+    profile: Option<Option<String>>,
+    // a path, or - for stdin
+    program: Option<String>,
+    args: Vec<String>,
+    help: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut opts = Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "-e" => match args.next() {
+                Some(expr) => opts.exprs.push(expr.clone()),
+                None => return Err("-e needs an expression".to_string()),
+            },
+            "--load" => match args.next() {
+                Some(path) => opts.loads.push(path.clone()),
+                None => return Err("--load needs a file".to_string()),
+            },
+            "-i" => opts.interactive = true,
+            "-h" | "--help" => opts.help = true,
+            "--no-prelude" => opts.no_prelude = true,
+            "--debug" => opts.debug = true,
+            "--trace" => opts.trace = true,
+            "--profile" => opts.profile = Some(None),
+            "--" => break,
+            _ if arg.starts_with("--profile=") => {
+                opts.profile = Some(Some(arg["--profile=".len()..].to_string()))
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option {}", arg))
+            }
+            _ if opts.exprs.is_empty() => {
+                opts.program = Some(arg.clone());
+                break;
+            }
+            _ => {
+                opts.args.push(arg.clone());
+                break;
+            }
+        }
+    }
+    // arguments after --, or after the program, are the program's own
+    if opts.program.is_none() && opts.exprs.is_empty() && opts.args.is_empty() {
+        opts.program = args.next().cloned();
+    }
+    opts.args.extend(args.cloned());
+    Ok(opts)
+}
 
 fn main() {
-    let mut env = REnv::new();
-    let mut rl = Editor::<()>::new();
-    match rl.load_history(".repl_history") {
-        _ => (),
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 1 && args[1] == "fmt" {
+        process::exit(fmt(&args[2..]));
     }
-    let std = env.load("stdlib/prelude.rs");
-    if let _RErr(_) = std.clone() {
-        println!("{}", std);
-        return;
+    let run_project = args.len() > 1 && args[1] == "run";
+    let test_project = args.len() > 1 && args[1] == "test";
+    // a broken manifest only stops the commands that need the project
+    let projects = match current_project() {
+        Ok(projects) => projects,
+        Err(e) if run_project || test_project => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("warning: ignoring {}", e);
+            vec![]
+        }
+    };
+    if test_project {
+        process::exit(runner::run(&args[2..], &projects));
     }
-    if let Some(path) = env::args().nth(1) {
-        let res = env.load(path);
-        if let _RErr(_) = res.clone() {
-            println!("{}", res);
+    let parsed = if run_project {
+        parse_args(&args[2..])
+    } else {
+        parse_args(&args[1..])
+    };
+    let mut opts = match parsed {
+        Ok(opts) if opts.help => {
+            println!("{}", USAGE);
+            return;
         }
-        return;
-    }
-    println!("{}", REPL0);
-    env.def("help", RBfn(help));
-    loop {
-        let readline = rl.readline(REPL1);
-        match readline {
-            Ok(line) => {
-                let linestr = line.as_str();
-                rl.add_history_entry(linestr);
-                rl.save_history(".repl_history").unwrap();
-                if !line.is_empty() && !line.starts_with(';') {
-                    println!("{}", rep(line, &mut env));
-                }
-            }
-            Err(ReadlineError::Interrupted) => break,
-            Err(ReadlineError::Eof) => break,
-            Err(err) => {
-                println!("Error: {:?}", err);
-                break;
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    if run_project {
+        let entry = match projects.first() {
+            Some(p) => p.entry.to_string_lossy().to_string(),
+            None => {
+                eprintln!("could not find {} in this directory or its parents", MANIFEST);
+                process::exit(2);
             }
+        };
+        // everything after the options is an argument to the entry point
+        if let Some(arg) = opts.program.replace(entry) {
+            opts.args.insert(0, arg);
         }
     }
+    let env = if opts.no_prelude {
+        Ok(REnv::new())
+    } else {
+        repl::prelude()
+    };
+    let mut env = match env {
+        Ok(env) => env,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    env.modules.borrow_mut().prelude = !opts.no_prelude;
+    env.modules.borrow_mut().projects = projects;
+    let script_args: Vec<RVal> = opts.args.iter().map(|a| RStr(&a[..])).collect();
+    env.def("*args*", RVecArgs!(script_args));
+    if opts.debug {
+        env.debugger.borrow_mut().step();
+    }
+    if opts.trace {
+        env.tracer.borrow_mut().all = true;
+    }
+    if opts.profile.is_some() {
+        env.profiler.borrow_mut().start();
+    }
+    env.update_hooks();
+    let res = run(&opts, &mut env);
+    // (exit) stops the program with an error that is not one to report
+    let mut exit = env.exit.get();
+    match &res {
+        Err(e) if exit.is_none() => eprintln!("{}", e),
+        _ => (),
+    }
+    let repl = opts.interactive || (opts.program.is_none() && opts.exprs.is_empty());
+    if repl && exit.is_none() {
+        if io::stdin().is_terminal() {
+            println!("{}", REPL0);
+        }
+        exit = repl::run(env.clone());
+    }
+    if let Some(path) = &opts.profile {
+        let stderr = &mut env.output.borrow_mut().stderr;
+        env.profiler.borrow_mut().stop(path.as_deref(), stderr.as_mut());
+    }
+    let _ = env.flush_output();
+    match exit {
+        Some(code) => process::exit(code),
+        None if res.is_err() => process::exit(1),
+        None => (),
+    }
 }
 
-fn help(_: &[RVal], _: &mut REnv) -> RVal {
-    println!(
-        r#"################################################################################
-
-# data types:
-  # Str: "Hello, World!"
-  # Sym: hello-world
-  # Bool: true | false
-  # Flt: 42.42
-  # Int: 42
-  # Lst: (1 2 3 4)
-  # Vec: [1 2 3 4]
-  # Map: {{:one 1 :two 2}}
-  # Fn: (fn (x y) (+ x y))
+// the project in the current directory and its dependencies, or none if there
+// is no risp.toml
+fn current_project() -> Result<Vec<Project>, String> {
+    let cwd = env::current_dir().map_err(|e| e.to_string())?;
+    match find_manifest(&cwd) {
+        Some(root) => read_project(&root),
+        None => Ok(vec![]),
+    }
+}
 
-# builtin functions:
-  # arithmetic: / * - + %
-  # bitwise: & | ~ ^
-  # io: read write
-  # logic: not and or ! && || = != < <= > >=
-  # cond: (Bool Any) ...
-  # while: (Bool Any)
-  # for: (Sym Num Num Any)
-  # at: get the nth element of a Vec
-  # head: get the first element of a Lst or a Vec
-  # rest: get a new version of a Lst or Vec without the head
-  # do: evaluate several expressions in sequence, return last one
-  # let: bind an expression to a label
-  # if: check a condition, return first expression if true, else the second
-  # quote: return a value without evaluating it first
-  # eval: evaluate a string or a list
-  # get: get an element from a Map using a key
-  # load: load a module
+// loads the files, evaluates the expressions and runs the program, stopping
+// at the first error, which is printed to stderr
+fn run(opts: &Options, env: &mut REnv) -> Result<(), RVal> {
+    for path in opts.loads.iter() {
+        if let e @ _RErr(_) = env.load(&path[..]) {
+            return Err(e);
+        }
+    }
+    for expr in opts.exprs.iter() {
+        match env.eval_source(&expr[..]) {
+            e @ _RErr(_) => return Err(e),
+            RNil => (),
+            RLst(vs) if vs.is_empty() => (),
+            res => env.write_out(&format!("{}\n", pprint(&res, print_width(env))))?,
+        }
+    }
+    let res = match opts.program.as_deref() {
+        Some("-") => {
+            let mut src = String::new();
+            match io::stdin().read_to_string(&mut src) {
+                Ok(_) => env.eval_source(strip_shebang(&src)),
+                Err(_) => RErr("could not read stdin"),
+            }
+        }
+        Some(path) => env.load(path),
+        None => return Ok(()),
+    };
+    match res {
+        e @ _RErr(_) => Err(e),
+        _ => Ok(()),
+    }
+}
 
-################################################################################"#
-    );
-    RLstArgs![vec![]]
+fn fmt(args: &[String]) -> i32 {
+    let check = args.iter().any(|a| a == "--check");
+    let paths: Vec<&String> = args.iter().filter(|a| *a != "--check").collect();
+    if paths.is_empty() {
+        let mut src = String::new();
+        if io::stdin().read_to_string(&mut src).is_err() {
+            eprintln!("could not read stdin");
+            return 2;
+        }
+        let out = match format_source(&src[..]) {
+            Ok(out) => out,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 2;
+            }
+        };
+        if check {
+            return if out == src { 0 } else { 1 };
+        }
+        print!("{}", out);
+        return 0;
+    }
+    let mut status = 0;
+    for path in paths {
+        let src = match fs::read_to_string(path) {
+            Ok(src) => src,
+            Err(_) => {
+                eprintln!("could not read {}", path);
+                status = 2;
+                continue;
+            }
+        };
+        let out = match format_source(&src[..]) {
+            Ok(out) => out,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                status = 2;
+                continue;
+            }
+        };
+        if out == src {
+            continue;
+        }
+        if check {
+            println!("{} is not formatted", path);
+            status = status.max(1);
+        } else if fs::write(path, out).is_err() {
+            eprintln!("could not write {}", path);
+            status = 2;
+        }
+    }
+    status
 }
@@ -0,0 +1,476 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use std::borrow::Cow::{self, Borrowed, Owned};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::time::Instant;
+
+use fnv::{FnvHashMap, FnvHashSet};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::{Context, Editor, Helper};
+
+use crate::risp::{
+    describe, eval_read, is_incomplete, is_special_form, parse_number, pprint, print_width,
+    read_all, REnv, RSym, RVal, RVal::*,
+};
+
+const PROMPT: &str = "# ";
+const CONTINUE: &str = ". ";
+const HISTORY: &str = ".repl_history";
+
+/******************************************************************************
+** @read-eval-print loop
+******************************************************************************/
+
+// a fresh environment with the standard library loaded
+pub fn prelude() -> Result<REnv, RVal> {
+    let mut env = REnv::new();
+    match env.load("stdlib/prelude") {
+        e @ _RErr(_) => Err(e),
+        _ => Ok(env),
+    }
+}
+
+struct Session {
+    env: REnv,
+    // names bound before the user typed anything
+    baseline: FnvHashSet<String>,
+    loaded: Vec<String>,
+}
+
+impl Session {
+    fn new(env: REnv) -> Self {
+        let baseline = env.symbols.keys().cloned().collect();
+        Session {
+            env,
+            baseline,
+            loaded: vec![],
+        }
+    }
+    fn user_bindings(&self) -> Vec<(String, RVal)> {
+        let mut vs: Vec<(String, RVal)> = self
+            .env
+            .symbols
+            .iter()
+            .filter(|(k, _)| !self.baseline.contains(*k))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        vs.sort_by(|a, b| a.0.cmp(&b.0));
+        vs
+    }
+    // a line of REPL output, which goes wherever the program's output does
+    fn say(&self, line: String) {
+        let _ = self.env.write_out(&(line + "\n"));
+        let _ = self.env.flush_output();
+    }
+    fn print(&self, val: &RVal) {
+        self.say(pprint(val, print_width(&self.env)));
+    }
+    fn eval_str(&mut self, src: &str) -> RVal {
+        match read_all(src) {
+            Ok(forms) => {
+                let mut res = RNil;
+                for form in forms.iter() {
+                    res = eval_read(form, &mut self.env);
+                }
+                res
+            }
+            Err(e) => e,
+        }
+    }
+}
+
+// runs until the end of the input, :quit or (exit), returning the status
+// given to (exit) if that is how it ended
+pub fn run(env: REnv) -> Option<i32> {
+    let mut rl = Editor::<RispHelper>::new();
+    // piped sessions neither use nor overwrite the history
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        let _ = rl.load_history(HISTORY);
+    }
+    let mut session = Session::new(env);
+    rl.set_helper(Some(RispHelper::new(&session.env)));
+    let mut input = String::new();
+    loop {
+        let readline = rl.readline(if input.is_empty() { PROMPT } else { CONTINUE });
+        match readline {
+            Ok(line) => {
+                if input.is_empty() && line.trim().is_empty() {
+                    continue;
+                }
+                input.push_str(line.trim_end_matches(&['\n', '\r'][..]));
+                input.push('\n');
+                if is_incomplete(&input[..]) {
+                    continue;
+                }
+                rl.add_history_entry(input.trim_end());
+                if interactive {
+                    let _ = rl.save_history(HISTORY);
+                }
+                let quit = match command(&input, &mut session) {
+                    Some(quit) => quit,
+                    None => {
+                        match read_all(&input[..]) {
+                            Ok(forms) => {
+                                for form in forms.iter() {
+                                    let res = eval_read(form, &mut session.env);
+                                    if session.env.exit.get().is_some() {
+                                        break;
+                                    }
+                                    session.print(&res);
+                                }
+                            }
+                            Err(e) => session.say(e.to_string()),
+                        }
+                        false
+                    }
+                };
+                if let Some(code) = session.env.exit.get() {
+                    return Some(code);
+                }
+                if quit {
+                    break;
+                }
+                if let Some(helper) = rl.helper_mut() {
+                    helper.refresh(&session.env);
+                }
+                input.clear();
+            }
+            Err(ReadlineError::Interrupted) if !input.is_empty() => input.clear(),
+            Err(ReadlineError::Interrupted) => break,
+            // what is left of an unfinished form cannot be read
+            Err(ReadlineError::Eof) if !input.is_empty() => {
+                if let Err(e) = read_all(&input[..]) {
+                    session.say(e.to_string());
+                }
+                break;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                session.say(format!("Error: {:?}", err));
+                break;
+            }
+        }
+    }
+    None
+}
+
+/******************************************************************************
+** @repl commands
+******************************************************************************/
+
+const COMMANDS: [(&str, &str, &str); 10] = [
+    (":env", "", "list the bindings made in this session"),
+    (":doc", "sym", "show the documentation of a symbol"),
+    (":type", "expr", "show the type of the value of an expression"),
+    (":time", "expr", "evaluate an expression and show how long it took"),
+    (":load", "file", "load a file into the session"),
+    (":reload", "", "load every file loaded with :load again"),
+    (":reset", "", "start over with a fresh environment"),
+    (":save", "file", "write the bindings made in this session as source"),
+    (":quit", "", "leave the REPL"),
+    (":help", "", "list the REPL commands"),
+];
+
+// runs input as a REPL command, returning whether to quit, or None if input
+// is not a command and should be evaluated
+fn command(input: &str, session: &mut Session) -> Option<bool> {
+    let input = input.trim();
+    let (cmd, arg) = match input.find(char::is_whitespace) {
+        Some(i) => (&input[..i], input[i..].trim()),
+        None => (input, ""),
+    };
+    if !COMMANDS.iter().any(|(c, _, _)| *c == cmd) {
+        return None;
+    }
+    match cmd {
+        ":env" => {
+            for (k, v) in session.user_bindings() {
+                session.say(format!("{} = {}", k, pprint(&v, print_width(&session.env))));
+            }
+        }
+        ":doc" => session.say(describe(arg, &session.env)),
+        ":type" => {
+            let res = session.eval_str(arg);
+            session.say(res.variant());
+        }
+        ":time" => {
+            let start = Instant::now();
+            let res = session.eval_str(arg);
+            let elapsed = start.elapsed();
+            session.print(&res);
+            session.say(format!("; {:?}", elapsed));
+        }
+        ":load" => {
+            let res = session.env.load(arg);
+            if let _RErr(_) = res {
+                session.print(&res);
+            } else if !session.loaded.iter().any(|f| f == arg) {
+                session.loaded.push(arg.to_string());
+            }
+        }
+        ":reload" => {
+            for path in session.loaded.clone() {
+                let res = session.env.load(&path[..]);
+                if let _RErr(_) = res {
+                    session.say(format!("{}: {}", path, res));
+                }
+            }
+        }
+        ":reset" => match prelude() {
+            Ok(env) => *session = Session::new(env),
+            Err(e) => session.say(e.to_string()),
+        },
+        ":save" => {
+            let src: Vec<String> = session
+                .user_bindings()
+                .iter()
+                .map(|(k, v)| match v {
+                    RBfn(_) => format!("; {} is bound to a builtin and was not saved", k),
+                    RPort(_) => format!("; {} is bound to a port and was not saved", k),
+                    _ => {
+                        let def = to_source(k, v, session.env.meta.get(k));
+                        pprint(&def, print_width(&session.env))
+                    }
+                })
+                .collect();
+            if let Err(e) = fs::write(arg, src.join("\n\n") + "\n") {
+                session.say(format!("could not write {}: {}", arg, e));
+            }
+        }
+        ":quit" => return Some(true),
+        _ => {
+            for (c, a, d) in COMMANDS.iter() {
+                session.say(format!("{:<16}{}", format!("{} {}", c, a), d));
+            }
+        }
+    }
+    Some(false)
+}
+
+// (let name value) that evaluates back to the same binding
+fn to_source(name: &str, val: &RVal, meta: Option<&RVal>) -> RVal {
+    let name = match meta {
+        Some(m) => RLstArgs!([RSym("with-meta"), RSym(name), m.clone()]),
+        None => RSym(name),
+    };
+    let val = match val {
+        RLfn(l) => {
+            let f = RLstArgs!([RSym("fn"), (*l.params).clone(), (*l.body).clone()]);
+            match &*l.meta {
+                RNil => f,
+                m => RLstArgs!([RSym("with-meta"), f, m.clone()]),
+            }
+        }
+        RLst(_) | _RSym(_) => RLstArgs!([RSym("quote"), val.clone()]),
+        _ => val.clone(),
+    };
+    RLstArgs!([RSym("let"), name, val])
+}
+
+/******************************************************************************
+** @line editor helper
+******************************************************************************/
+
+pub struct RispHelper {
+    names: Vec<String>,
+    params: FnvHashMap<String, String>,
+}
+
+impl RispHelper {
+    pub fn new(env: &REnv) -> Self {
+        let mut helper = RispHelper {
+            names: vec![],
+            params: FnvHashMap::default(),
+        };
+        helper.refresh(env);
+        helper
+    }
+    // refresh the known symbols after the environment changed
+    pub fn refresh(&mut self, env: &REnv) {
+        self.names = env.symbols.keys().cloned().collect();
+        self.names.extend(env.docs.keys().cloned());
+        self.names.sort();
+        self.names.dedup();
+        self.params = env
+            .docs
+            .iter()
+            .map(|(k, d)| (k.clone(), format!("({})", d.signature)))
+            .collect();
+        self.params.extend(env.symbols.iter().filter_map(|(k, v)| match v {
+            RLfn(l) => Some((k.clone(), l.params.to_string())),
+            _ => None,
+        }));
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !"()[]{}\"'`,;".contains(c)
+}
+
+// start of the word the cursor is in
+fn word_start(line: &str, pos: usize) -> usize {
+    line[..pos]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !is_word_char(*c))
+        .map_or(0, |(i, c)| i + c.len_utf8())
+}
+
+impl Completer for RispHelper {
+    type Candidate = String;
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = word_start(line, pos);
+        let word = &line[start..pos];
+        let candidates = self
+            .names
+            .iter()
+            .filter(|n| n.starts_with(word))
+            .cloned()
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for RispHelper {
+    // (fact|          =>  (fact| (n)
+    // (facto|         =>  (facto|rial
+    fn hint(&self, line: &str, pos: usize, _: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+        let start = word_start(line, pos);
+        if start == 0 || !line[..start].ends_with('(') || start == pos {
+            return None;
+        }
+        let word = &line[start..pos];
+        if let Some(params) = self.params.get(word) {
+            return Some(format!(" {}", params));
+        }
+        let mut matches = self.names.iter().filter(|n| n.starts_with(word));
+        match (matches.next(), matches.next()) {
+            (Some(name), None) if name.len() > word.len() => Some(name[word.len()..].to_string()),
+            _ => None,
+        }
+    }
+}
+
+/******************************************************************************
+** @syntax highlighting
+******************************************************************************/
+
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[33m";
+const KEYWORD: &str = "\x1b[35m";
+const SYMBOL_KEY: &str = "\x1b[36m";
+const COMMENT: &str = "\x1b[90m";
+const BRACKET: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+// byte offsets of the bracket under or just before the cursor and its match
+fn matching_brackets(pos: usize, brackets: &[(usize, char)]) -> Option<(usize, usize)> {
+    let at = brackets
+        .iter()
+        .position(|(i, _)| *i == pos)
+        .or_else(|| brackets.iter().position(|(i, _)| *i + 1 == pos))?;
+    let mut stack = vec![];
+    for (n, (i, c)) in brackets.iter().enumerate() {
+        if "([{".contains(*c) {
+            stack.push(n);
+        } else if let Some(open) = stack.pop() {
+            if open == at || n == at {
+                return Some((brackets[open].0, *i));
+            }
+        }
+    }
+    None
+}
+
+impl Highlighter for RispHelper {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        let mut spans: Vec<(usize, usize, &str)> = vec![];
+        let mut brackets = vec![];
+        let mut chars = line.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    let mut end = line.len();
+                    let mut escaped = false;
+                    for (j, d) in chars.by_ref() {
+                        if d == '"' && !escaped {
+                            end = j + 1;
+                            break;
+                        }
+                        escaped = d == '\\' && !escaped;
+                    }
+                    spans.push((i, end, STRING));
+                }
+                ';' => {
+                    spans.push((i, line.len(), COMMENT));
+                    break;
+                }
+                '(' | ')' | '[' | ']' | '{' | '}' => brackets.push((i, c)),
+                _ if is_word_char(c) => {
+                    let mut end = i + c.len_utf8();
+                    while let Some((j, d)) = chars.peek() {
+                        if !is_word_char(*d) {
+                            break;
+                        }
+                        end = j + d.len_utf8();
+                        chars.next();
+                    }
+                    let word = &line[i..end];
+                    let color = if parse_number(word).is_some() {
+                        NUMBER
+                    } else if word.starts_with(':') {
+                        SYMBOL_KEY
+                    } else if is_special_form(word) {
+                        KEYWORD
+                    } else {
+                        continue;
+                    };
+                    spans.push((i, end, color));
+                }
+                _ => (),
+            }
+        }
+        if let Some((open, close)) = matching_brackets(pos, &brackets) {
+            spans.push((open, open + 1, BRACKET));
+            spans.push((close, close + 1, BRACKET));
+            spans.sort();
+        }
+        if spans.is_empty() {
+            return Borrowed(line);
+        }
+        let mut out = String::with_capacity(line.len() * 2);
+        let mut last = 0;
+        for (start, end, color) in spans {
+            out.push_str(&line[last..start]);
+            out.push_str(color);
+            out.push_str(&line[start..end]);
+            out.push_str(RESET);
+            last = end;
+        }
+        out.push_str(&line[last..]);
+        Owned(out)
+    }
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Owned(format!("{}{}{}", COMMENT, hint, RESET))
+    }
+    fn highlight_char(&self, line: &str, _: usize) -> bool {
+        !line.is_empty()
+    }
+}
+
+impl Helper for RispHelper {}
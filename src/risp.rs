@@ -22,5 +22,47 @@ pub use self::arithmetic::*;
 mod io;
 pub use self::io::*;
 
+mod files;
+pub use self::files::*;
+
+mod port;
+pub use self::port::*;
+
+mod output;
+pub use self::output::*;
+
 mod constructs;
 pub use self::constructs::*;
+
+mod pprint;
+pub use self::pprint::*;
+
+mod fmt;
+pub use self::fmt::*;
+
+mod meta;
+pub use self::meta::*;
+
+mod doc;
+pub use self::doc::*;
+
+mod debug;
+pub use self::debug::*;
+
+mod trace;
+pub use self::trace::*;
+
+mod profile;
+pub use self::profile::*;
+
+mod testing;
+pub use self::testing::*;
+
+mod stdlib;
+pub use self::stdlib::*;
+
+mod module;
+pub use self::module::*;
+
+mod manifest;
+pub use self::manifest::*;
@@ -90,18 +90,18 @@ macro_rules! rval_impl_ish {
 ******************************************************************************/
 
 pub fn load_arithmetic(env: &mut REnv) {
-    env.def("+", RBfn(add));
-    env.def("/", RBfn(div));
-    env.def("*", RBfn(mul));
-    env.def("-", RBfn(sub));
-    env.def("%", RBfn(rem));
-    env.def("&", RBfn(bitand));
-    env.def("|", RBfn(bitor));
-    env.def("~", RBfn(not));
-    env.def("^", RBfn(bitxor));
-    env.def("<<", RBfn(shl));
-    env.def(">>", RBfn(shr));
-    env.def("floor", RBfn(floor));
+    env.defn("+", add, "Num ...", "add numbers, 0 if there are none");
+    env.defn("/", div, "Num Num ...", "divide the first number by the rest");
+    env.defn("*", mul, "Num ...", "multiply numbers, 1 if there are none");
+    env.defn("-", sub, "Num Num ...", "subtract the rest of the numbers from the first");
+    env.defn("%", rem, "Int Int ...", "remainder of dividing the first integer by the rest");
+    env.defn("&", bitand, "Int Int ...", "bitwise and");
+    env.defn("|", bitor, "Int Int ...", "bitwise or");
+    env.defn("~", not, "Int ...", "bitwise not of every integer, as a list");
+    env.defn("^", bitxor, "Int Int ...", "bitwise exclusive or");
+    env.defn("<<", shl, "Int Int ...", "shift the first integer left");
+    env.defn(">>", shr, "Int Int ...", "shift the first integer right");
+    env.defn("floor", floor, "Num ...", "round every number down to an integer, as a list");
 }
 
 macro_rules! rval_binop {
@@ -182,7 +182,7 @@ fn not(args: &[RVal], env: &mut REnv) -> RVal {
     } else {
         let mut out = vec![];
         for v in args.iter() {
-            match eval(&v, env) {
+            match eval(v, env) {
                 RInt(i) => out.push(RInt(i.not())),
                 _ => return RErrExpected!("(Num ...)", RLstArgs!(args).variant()),
             };
@@ -197,7 +197,7 @@ fn floor(args: &[RVal], env: &mut REnv) -> RVal {
     } else {
         let mut out = vec![];
         for v in args.iter() {
-            match eval(&v, env) {
+            match eval(v, env) {
                 RInt(i) => out.push(RInt(i)),
                 RFlt(f) => out.push(RInt(f.floor() as i64)),
                 _ => return RErrExpected!("(Num ...)", RLstArgs!(args).variant()),
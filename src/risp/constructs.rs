@@ -1,11 +1,31 @@
 use crate::risp::{eval, REnv, RErr, RVal, RVal::*};
 
 pub fn load_constructs(env: &mut REnv) {
-    env.def("cons", RBfn(ccons));
-    env.def("cond", RBfn(ccond));
-    env.def("if", RBfn(cif));
-    env.def("for", RBfn(cfor));
-    env.def("while", RBfn(cwhile));
+    env.defn("cons", ccons, "Any Any", "make a list of two values, leaving out empty lists");
+    env.defn(
+        "cond",
+        ccond,
+        "(Bool Any) ...",
+        "evaluate the expression of the first pair whose condition is true",
+    );
+    env.defn(
+        "if",
+        cif,
+        "Bool Any Any?",
+        "evaluate the first expression if the condition is true, else the second",
+    );
+    env.defn(
+        "for",
+        cfor,
+        "Sym Int Int Any",
+        "evaluate an expression with a symbol bound to each integer in a range",
+    );
+    env.defn(
+        "while",
+        cwhile,
+        "Bool Any",
+        "evaluate an expression as long as the condition is true",
+    );
 }
 
 fn ccons(xs: &[RVal], env: &mut REnv) -> RVal {
@@ -13,8 +33,8 @@ fn ccons(xs: &[RVal], env: &mut REnv) -> RVal {
         2 => {
             let mut vs = vec![];
             for v in xs.iter() {
-                if v != &RLstArgs![vec![]] {
-                    vs.push(eval(&v, env));
+                if v != &RLstArgs![[]] {
+                    vs.push(eval(v, env));
                 }
             }
             RLstArgs![vs]
@@ -34,7 +54,7 @@ fn ccond(xs: &[RVal], env: &mut REnv) -> RVal {
                         if b {
                             return eval(&vs[1], env);
                         } else {
-                            RLstArgs![vec![]]
+                            RLstArgs![[]]
                         }
                     } else {
                         RErrExpected!("(Bool Any)", pair.variant())
@@ -44,11 +64,11 @@ fn ccond(xs: &[RVal], env: &mut REnv) -> RVal {
                 }
                 _ => RErrExpected!("(Bool Any)", pair.variant())
             };
-            if tmp != RLstArgs![vec![]] {
+            if tmp != RLstArgs![[]] {
                 return tmp.clone();
             }
         }
-        RLstArgs![vec![]]
+        RLstArgs![[]]
     }
 }
 
@@ -58,7 +78,7 @@ fn cif(xs: &[RVal], env: &mut REnv) -> RVal {
            RBool(b) => if b {
                eval(&xs[1], env)
            } else {
-               RLstArgs![vec![]]
+               RLstArgs![[]]
            }
            _ => RErrExpected!("(Bool Any)", RLstArgs![xs].variant()),
        }
@@ -81,7 +101,7 @@ fn cfor(xs: &[RVal], env: &mut REnv) -> RVal {
                 let from = if _from < _to { _from } else { _to };
                 let to = if _to > _from { _to } else { _from };
                 let past = env.get(&s[..]);
-                let mut out = RLstArgs![vec![]];
+                let mut out = RLstArgs![[]];
                 for it in from..to {
                     env.def(&s[..], RInt(it));
                     out = eval(&xs[3], env);
@@ -99,7 +119,7 @@ fn cwhile(xs: &[RVal], env: &mut REnv) -> RVal {
     match xs.len() {
         2 => match eval(&xs[0], env) {
             RBool(b) => {
-                let mut out = RLstArgs![vec![]];
+                let mut out = RLstArgs![[]];
                 let mut cond = b;
                 while cond {
                     out = eval(&xs[1], env);
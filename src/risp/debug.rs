@@ -0,0 +1,216 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+extern crate rustyline;
+use rustyline::Editor;
+
+use std::io::{self, IsTerminal};
+use std::rc::Rc;
+
+use crate::risp::{
+    eval_read, pprint, print_width, read_all, read_stdin_line, REnv, RErr, RVal, RVal::*,
+};
+
+/******************************************************************************
+** @debugger
+******************************************************************************/
+
+// a call to a lambda being evaluated
+pub struct Frame {
+    pub name: RVal,
+    pub params: Rc<RVal>,
+    pub args: Vec<RVal>,
+}
+
+impl Frame {
+    pub fn call(&self) -> RVal {
+        let mut vs = vec![self.name.clone()];
+        vs.extend(self.args.iter().cloned());
+        RLstArgs!(vs)
+    }
+}
+
+// where evaluation pauses next, depths are the number of frames the call
+// is made from
+#[derive(Clone, Copy)]
+enum Mode {
+    Run,
+    Step,
+    Over(usize),
+    Out(usize),
+}
+
+pub struct Debugger {
+    // the calls made since the debugger was enabled, by --debug or the first
+    // (break)
+    pub stack: Vec<Frame>,
+    pub enabled: bool,
+    mode: Mode,
+    // kept for the whole session so the prompt has a history
+    editor: Option<Editor<()>>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Debugger {
+            stack: vec![],
+            enabled: false,
+            mode: Mode::Run,
+            editor: None,
+        }
+    }
+    // pause at the next call to a lambda
+    pub fn step(&mut self) {
+        self.enabled = true;
+        self.mode = Mode::Step;
+    }
+    fn stops_at(&self, depth: usize) -> bool {
+        match self.mode {
+            Mode::Run => false,
+            Mode::Step => true,
+            Mode::Over(d) => depth <= d,
+            Mode::Out(d) => depth < d,
+        }
+    }
+    // a line typed at the prompt, None at the end of the input; piped input
+    // is read a line at a time
+    fn read_command(&mut self, prompt: &str) -> Option<String> {
+        if !io::stdin().is_terminal() {
+            return read_stdin_line().ok().flatten();
+        }
+        let rl = self.editor.get_or_insert_with(Editor::new);
+        let line = rl.readline(prompt).ok()?;
+        rl.add_history_entry(line.as_str());
+        Some(line)
+    }
+}
+
+const COMMANDS: [(&str, &str); 7] = [
+    ("step, s", "continue until the next call, entering this one"),
+    ("next, n", "continue until the next call made from this frame"),
+    ("out, o", "continue until the next call made by the caller"),
+    ("continue, c", "continue until the next (break)"),
+    ("backtrace, bt", "show the call stack"),
+    ("locals, l", "show the arguments of the current call"),
+    ("help, h", "list the debugger commands"),
+];
+
+// a line of debugger output, which goes wherever the program's output does;
+// failing to write it does not stop the program
+fn say(env: &REnv, line: String) {
+    let _ = env.write_out(&(line + "\n"));
+    let _ = env.flush_output();
+}
+
+// called by eval_lambda once the frame of a call is pushed, before its body
+// is evaluated in env
+pub fn before_call(env: &mut REnv) {
+    let depth = {
+        let dbg = env.debugger.borrow();
+        // the number of frames the call is made from
+        let depth = dbg.stack.len().saturating_sub(1);
+        if !dbg.stops_at(depth) {
+            return;
+        }
+        match dbg.stack.last() {
+            Some(f) => say(env, format!("-> {}", f.call())),
+            None => return,
+        }
+        depth
+    };
+    prompt(depth, env);
+}
+
+// the nested prompt, where any input that is not a command is evaluated in
+// the environment of the paused call; depth is where n and o stop, while the
+// prompt shows the index of the current frame, the same for a step into a
+// call and a (break) in its body
+fn prompt(depth: usize, env: &mut REnv) {
+    env.debugger.borrow_mut().mode = Mode::Run;
+    let frame = env.debugger.borrow().stack.len().saturating_sub(1);
+    let mode = loop {
+        let prompt = format!("debug:{}> ", frame);
+        let line = env.debugger.borrow_mut().read_command(&prompt);
+        let line = match line {
+            Some(line) => line,
+            None => break Mode::Run,
+        };
+        // piped commands are echoed after the prompt, as a terminal would
+        if !io::stdin().is_terminal() {
+            say(env, format!("{}{}", prompt, line));
+        }
+        match line.trim() {
+            "" => (),
+            "step" | "s" => break Mode::Step,
+            "next" | "n" => break Mode::Over(depth),
+            "out" | "o" => break Mode::Out(depth),
+            "continue" | "c" => break Mode::Run,
+            "backtrace" | "bt" => {
+                let dbg = env.debugger.borrow();
+                for (i, f) in dbg.stack.iter().rev().enumerate() {
+                    say(env, format!("#{} {}", i, f.call()));
+                }
+            }
+            "locals" | "l" => {
+                let dbg = env.debugger.borrow();
+                if let Some(f) = dbg.stack.last() {
+                    if let RLst(ps) = &*f.params {
+                        for (p, v) in ps.iter().zip(f.args.iter()) {
+                            say(env, format!("{} = {}", p, v));
+                        }
+                    }
+                }
+            }
+            "help" | "h" => {
+                for (c, d) in COMMANDS.iter() {
+                    say(env, format!("{:<16}{}", c, d));
+                }
+                say(env, format!("{:<16}evaluate an expression in this frame", "expr"));
+            }
+            src => match read_all(src) {
+                Ok(forms) => {
+                    for form in forms.iter() {
+                        let res = eval_read(form, env);
+                        if env.exit.get().is_some() {
+                            break;
+                        }
+                        say(env, pprint(&res, print_width(env)));
+                    }
+                    // (exit) at the prompt ends the program
+                    if env.exit.get().is_some() {
+                        break Mode::Run;
+                    }
+                }
+                Err(e) => say(env, e.to_string()),
+            },
+        }
+    };
+    env.debugger.borrow_mut().mode = mode;
+}
+
+/******************************************************************************
+** @debugger into environment
+******************************************************************************/
+
+pub fn load_debug(env: &mut REnv) {
+    env.defn("break", breakpoint, "", "pause and open the debugger here");
+}
+
+fn breakpoint(args: &[RVal], env: &mut REnv) -> RVal {
+    if !args.is_empty() {
+        return RErrExpected!("()", RLstArgs![args].variant());
+    }
+    env.debugger.borrow_mut().enabled = true;
+    env.update_hooks();
+    let depth = env.debugger.borrow().stack.len();
+    match env.debugger.borrow().stack.last() {
+        Some(f) => say(env, format!("break in {}", f.call())),
+        None => say(env, "break".to_string()),
+    }
+    prompt(depth, env);
+    match env.exit.get() {
+        Some(_) => RErr("exit"),
+        None => RLstArgs![[]],
+    }
+}
@@ -0,0 +1,193 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use crate::risp::{eval, REnv, RErr, RStr, RSym, RVal, RVal::*, SPECIAL_FORMS};
+
+/******************************************************************************
+** @documentation
+******************************************************************************/
+
+#[derive(Clone)]
+pub struct RDoc {
+    pub signature: String,
+    pub doc: String,
+}
+
+const OVERVIEW: &str = r#"# data types:
+  # Str: "Hello, World!"
+  # Sym: hello-world
+  # Bool: true | false
+  # Flt: 42.42
+  # Int: 42
+  # Lst: (1 2 3 4)
+  # Vec: [1 2 3 4]
+  # Map: {:one 1 :two 2}
+  # Fn: (fn "docstring" (x y) (+ x y))
+
+# reader syntax:
+  # 'x is (quote x), #'x is (var x)
+  # #_ skips the next form, #| ... |# is a block comment
+  # #tag x reads as what the reader registered with defreader returns for x
+  # ^{:doc "..."} x is (with-meta x {:doc "..."}), ^:k x is ^{:k true} x
+
+# (help sym) describes a function, (apropos "str") finds functions by name"#;
+
+// the special forms are never bound, their documentation comes from the
+// table try_builtin dispatches on
+pub fn special_form_docs() -> Vec<(String, RDoc)> {
+    SPECIAL_FORMS
+        .iter()
+        .map(|(name, signature, doc)| {
+            let d = RDoc {
+                signature: signature.to_string(),
+                doc: doc.to_string(),
+            };
+            (name.to_string(), d)
+        })
+        .collect()
+}
+
+// docstrings spanning several lines are indented to line up in the source
+fn dedent(doc: &str) -> String {
+    doc.lines().map(str::trim).collect::<Vec<&str>>().join("\n")
+}
+
+// examples in a docstring, written as (expr) ;=> value on a line of their own
+pub fn doc_examples(doc: &str) -> Vec<(String, String)> {
+    doc.lines()
+        .filter_map(|line| line.split_once(";=>"))
+        .map(|(expr, val)| (expr.trim().to_string(), val.trim().to_string()))
+        .collect()
+}
+
+// the docstring of a function bound to name, or of a builtin
+pub fn doc_of(name: &str, env: &REnv) -> Option<RDoc> {
+    if let Some(d) = env.docs.get(name) {
+        if let Some(RBfn(_)) | None = env.get(name) {
+            return Some(d.clone());
+        }
+    }
+    let doc = match env.meta.get(name) {
+        Some(RMap(m)) => m.get(&RSym(":doc")).cloned(),
+        _ => None,
+    };
+    match env.get(name) {
+        Some(RLfn(l)) => {
+            let doc = doc.or_else(|| match &*l.meta {
+                RMap(m) => m.get(&RSym(":doc")).cloned(),
+                _ => None,
+            });
+            let params = l.params.to_string();
+            Some(RDoc {
+                signature: params[1..params.len() - 1].to_string(),
+                doc: match doc {
+                    Some(_RStr(s)) => dedent(&s),
+                    _ => String::new(),
+                },
+            })
+        }
+        Some(_) => doc.map(|d| RDoc {
+            signature: String::new(),
+            doc: match d {
+                _RStr(s) => dedent(&s),
+                d => d.to_string(),
+            },
+        }),
+        None => None,
+    }
+}
+
+// (name signature)
+//   doc
+pub fn describe(name: &str, env: &REnv) -> String {
+    match doc_of(name, env) {
+        Some(d) => {
+            let usage = if d.signature.is_empty() {
+                format!("({})", name)
+            } else {
+                format!("({} {})", name, d.signature)
+            };
+            if d.doc.is_empty() {
+                usage
+            } else {
+                format!("{}\n  {}", usage, d.doc.replace('\n', "\n  "))
+            }
+        }
+        None => format!("no documentation for {}", name),
+    }
+}
+
+// every documented name, sorted
+pub fn documented(env: &REnv) -> Vec<String> {
+    let mut names: Vec<String> = env.docs.keys().cloned().collect();
+    names.extend(
+        env.symbols
+            .iter()
+            .filter(|(k, v)| match v {
+                RLfn(_) => true,
+                _ => env.meta.contains_key(*k),
+            })
+            .map(|(k, _)| k.clone()),
+    );
+    names.sort();
+    names.dedup();
+    names
+}
+
+/******************************************************************************
+** @documentation into environment
+******************************************************************************/
+
+pub fn load_doc(env: &mut REnv) {
+    env.defn("help", help, "Sym?", "describe a function, or the language");
+    env.defn("doc", doc, "Sym", "get the docstring of a function");
+    env.defn("apropos", apropos, "Str", "list the documented names containing a string");
+}
+
+fn help(args: &[RVal], env: &mut REnv) -> RVal {
+    let text = match args {
+        [] => {
+            let mut text = format!("{}\n\n# functions:\n", OVERVIEW);
+            for name in documented(env) {
+                text.push_str(&format!("  {}\n", describe(&name, env).replace('\n', "\n  ")));
+            }
+            text
+        }
+        [_RSym(s)] => format!("{}\n", describe(s, env)),
+        _ => return RErrExpected!("(Sym?)", RLstArgs![args].variant()),
+    };
+    match env.write_out(&text) {
+        Ok(()) => RLstArgs![[]],
+        Err(e) => e,
+    }
+}
+
+fn doc(args: &[RVal], env: &mut REnv) -> RVal {
+    match args {
+        [_RSym(s)] => match doc_of(s, env) {
+            Some(d) if !d.doc.is_empty() => RStr(d.doc),
+            Some(_) => RNil,
+            None if env.get(&s[..]).is_some() => RNil,
+            None => RErrUnboundSymbol!(s),
+        },
+        _ => RErrExpected!("(Sym)", RLstArgs![args].variant()),
+    }
+}
+
+fn apropos(args: &[RVal], env: &mut REnv) -> RVal {
+    match args {
+        [x] => match eval(x, env) {
+            _RStr(s) => {
+                let found: Vec<RVal> = documented(env)
+                    .iter()
+                    .filter(|n| n.contains(&s[..]))
+                    .map(RSym)
+                    .collect();
+                RLstArgs!(found)
+            }
+            v => RErrExpected!("(Str)", v.variant()),
+        },
+        _ => RErrExpected!("(Str)", RLstArgs![args].variant()),
+    }
+}
@@ -2,7 +2,10 @@
 ** @crates and modules
 ******************************************************************************/
 
-use crate::risp::{parse, tokenize, REnv, RErr, RLambda, RVal, RVal::*};
+use crate::risp::{
+    before_call, expand_readers, parse, profile_enter, profile_exit, tokenize, trace_call,
+    trace_return, Frame, REnv, RErr, RLambda, RVal, RVal::*,
+};
 
 /******************************************************************************
 ** @read-eval-print
@@ -15,7 +18,15 @@ where
     let tokens = tokenize(expr.into());
     let parsed = parse(&tokens);
     match parsed {
-        Ok(v) => eval(&v.0, env),
+        Ok(v) => eval_read(&v.0, env),
+        Err(e) => e,
+    }
+}
+
+// evaluates a form as the parser returned it, expanding its #tag forms first
+pub fn eval_read(form: &RVal, env: &mut REnv) -> RVal {
+    match expand_readers(form, env) {
+        Ok(x) => eval(&x, env),
         Err(e) => e,
     }
 }
@@ -30,10 +41,7 @@ pub fn eval(val: &RVal, env: &mut REnv) -> RVal {
             if s.starts_with(':') {
                 return val.clone();
             }
-            let _r = env
-                .get(&s[..])
-                .ok_or_else(|| RErrUnboundSymbol!(s))
-                .map(|x| x.clone());
+            let _r = env.get(&s[..]).ok_or_else(|| RErrUnboundSymbol!(s));
             match _r {
                 Ok(v) => v,
                 Err(e) => e,
@@ -41,37 +49,94 @@ pub fn eval(val: &RVal, env: &mut REnv) -> RVal {
         }
         RLst(vs) => {
             if vs.is_empty() {
-                return RLstArgs!(vec![]);
+                return RLstArgs!([]);
             }
             let x = &vs[0];
             let xs = &vs[1..];
-            let is_builtin = env.try_builtin(x, xs);
-            match is_builtin {
-                RNil => match env.is_function(&x) {
-                    RBfn(f) => f(xs, env),
-                    RLfn(lambda) => eval_lambda(&lambda, xs, env),
+            match env.try_builtin(x, xs) {
+                Some(res) => res,
+                None => match env.is_function(x) {
+                    RBfn(f) => eval_builtin(x, f, xs, env),
+                    RLfn(lambda) => eval_lambda(x, &lambda, xs, env),
                     _ => RErrExpected!("(Fn)", x.variant()),
                 },
-                _ => is_builtin,
             }
         }
         _ => val.clone(),
     }
 }
 
-pub fn eval_lambda(lambda: &RLambda, args: &[RVal], env: &REnv) -> RVal {
+// name is the form the function was called as, used by the debugger and
+// when tracing or profiling
+pub fn eval_builtin(
+    name: &RVal,
+    f: fn(&[RVal], &mut REnv) -> RVal,
+    args: &[RVal],
+    env: &mut REnv,
+) -> RVal {
+    if !env.hooked.get() {
+        return f(args, env);
+    }
+    let traced = trace_call(name, args, env);
+    let profiled = profile_enter(name, env);
+    let res = f(args, env);
+    if profiled {
+        profile_exit(env);
+    }
+    if traced {
+        trace_return(&res, env);
+    }
+    res
+}
+
+pub fn eval_lambda(name: &RVal, lambda: &RLambda, args: &[RVal], env: &mut REnv) -> RVal {
     if args.len() == lambda.params.len() {
         match &*lambda.params {
             RLst(vs) => {
-                let mut new_env = env.clone();
+                let hooked = env.hooked.get();
+                let mut new_env = match &lambda.module {
+                    Some(scope) => env.in_scope(scope.clone()),
+                    None => env.clone(),
+                };
+                let mut vals = Vec::with_capacity(args.len());
                 for (k, v) in vs.iter().zip(args.iter()) {
-                    let new_val = eval(&v.clone(), &mut new_env);
+                    // a module's function gets its arguments from the caller
+                    let new_val = match &lambda.module {
+                        Some(_) => eval(v, env),
+                        None => eval(v, &mut new_env),
+                    };
+                    if hooked {
+                        vals.push(new_val.clone());
+                    }
                     match &k {
                         _RSym(s) => new_env.def(&s[..], new_val),
                         _ => return RErr("internal error (eval_lambda)"),
                     };
                 }
-                eval(&lambda.body, &mut new_env)
+                if !hooked {
+                    return eval(&lambda.body, &mut new_env);
+                }
+                let traced = trace_call(name, &vals, env);
+                let profiled = profile_enter(name, env);
+                env.debugger.borrow_mut().stack.push(Frame {
+                    name: name.clone(),
+                    params: lambda.params.clone(),
+                    args: vals,
+                });
+                before_call(&mut new_env);
+                // (exit) typed at the debugger prompt
+                let res = match env.exit.get() {
+                    Some(_) => RErr("exit"),
+                    None => eval(&lambda.body, &mut new_env),
+                };
+                env.debugger.borrow_mut().stack.pop();
+                if profiled {
+                    profile_exit(env);
+                }
+                if traced {
+                    trace_return(&res, env);
+                }
+                res
             }
             _ => RErr("internal error (eval_lambda)"),
         }
@@ -0,0 +1,356 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{self, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::risp::{eval, REnv, RErr, RStr, RSym, RVal, RVal::*};
+
+/******************************************************************************
+** @files and paths
+******************************************************************************/
+
+// an I/O failure as "op: path: reason", the same for every operation
+fn io_error(op: &str, path: &str, e: io::Error) -> RVal {
+    RErr(format!("{}: {}: {}", op, path, e.kind()))
+}
+
+fn path_str(path: &Path) -> RVal {
+    RStr(path.to_string_lossy().to_string())
+}
+
+// the path an argument evaluates to
+fn path_arg(x: &RVal, env: &mut REnv) -> Result<String, RVal> {
+    match eval(x, env) {
+        _RStr(s) => Ok(s.to_string()),
+        e @ _RErr(_) => Err(e),
+        v => Err(RErrExpected!("Str", v.variant())),
+    }
+}
+
+// paths given as one Str argument
+fn with_path(args: &[RVal], env: &mut REnv, f: fn(&str) -> RVal) -> RVal {
+    match args {
+        [x] => match path_arg(x, env) {
+            Ok(path) => f(&path),
+            Err(e) => e,
+        },
+        _ => RErrExpected!("(Str)", RLstArgs![args].variant()),
+    }
+}
+
+// the entries of a directory, sorted by name
+fn entries(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    // an empty path is the current directory, without ./ in front of names
+    let read = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let mut paths = vec![];
+    for entry in fs::read_dir(read)? {
+        paths.push(dir.join(entry?.file_name()));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+// a directory that is not a symlink; walks list links to directories but do
+// not follow them, so a link to a parent cannot loop
+fn real_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.is_dir())
+}
+
+fn walk(dir: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    for path in entries(dir)? {
+        found.push(path.clone());
+        if real_dir(&path) {
+            walk(&path, found)?;
+        }
+    }
+    Ok(())
+}
+
+// whether name matches a pattern where * is any run of characters and ? any
+// one character
+fn wildcard(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            wildcard(&pattern[1..], name) || (!name.is_empty() && wildcard(pattern, &name[1..]))
+        }
+        (Some('?'), Some(_)) => wildcard(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => wildcard(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+// the paths under dir matching the remaining components of a glob, where **
+// matches any number of directories, or everything when it comes last; names
+// starting with . only match components that do too, and ** does not follow
+// links to directories
+fn expand(dir: PathBuf, parts: &[&str], found: &mut Vec<PathBuf>) {
+    let part = match parts.first() {
+        Some(part) => *part,
+        // the current directory a relative pattern starts from is not a match
+        None if dir.as_os_str().is_empty() => return,
+        None => return found.push(dir),
+    };
+    if !part.contains(['*', '?']) {
+        let path = dir.join(part);
+        if path.exists() {
+            expand(path, &parts[1..], found);
+        }
+        return;
+    }
+    let paths = entries(&dir).unwrap_or_default();
+    if part == "**" {
+        expand(dir, &parts[1..], found);
+    }
+    let pattern: Vec<char> = part.chars().collect();
+    for path in paths {
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        if name.starts_with('.') && !part.starts_with('.') {
+            continue;
+        }
+        if part == "**" {
+            if real_dir(&path) {
+                expand(path, parts, found);
+            } else if parts.len() == 1 {
+                found.push(path);
+            }
+        } else if wildcard(&pattern, &name.chars().collect::<Vec<char>>()) {
+            expand(path, &parts[1..], found);
+        }
+    }
+}
+
+fn glob_paths(pattern: &str) -> Vec<PathBuf> {
+    let (root, rest) = match pattern.strip_prefix('/') {
+        Some(rest) => (PathBuf::from("/"), rest),
+        None => (PathBuf::new(), pattern),
+    };
+    let parts: Vec<&str> = rest.split('/').filter(|p| !p.is_empty()).collect();
+    let mut found = vec![];
+    expand(root, &parts, &mut found);
+    found.sort();
+    found.dedup();
+    found
+}
+
+/******************************************************************************
+** @files and paths into environment
+******************************************************************************/
+
+pub fn load_files(env: &mut REnv) {
+    env.defn("read-file", read_file, "Str", "read the contents of a file as a string");
+    env.defn(
+        "write-file",
+        write_file,
+        "Str Any",
+        "replace the contents of a file, strings are written without quotes",
+    );
+    env.defn(
+        "append-file",
+        append_file,
+        "Str Any",
+        "add to the end of a file, creating it if needed",
+    );
+    env.defn("file-exists?", file_exists, "Str", "true if a file or directory exists");
+    env.defn("delete-file", delete_file, "Str", "remove a file");
+    env.defn(
+        "list-dir",
+        list_dir,
+        "Str",
+        "the names of the entries of a directory as a sorted Vec",
+    );
+    env.defn("make-dir", make_dir, "Str", "create a directory and its missing parents");
+    env.defn(
+        "walk-dir",
+        walk_dir,
+        "Str",
+        "the paths of everything under a directory as a sorted Vec,\n\
+         without following links to directories",
+    );
+    env.defn(
+        "glob",
+        glob,
+        "Str",
+        "the paths matching a pattern of *, ? and ** as a sorted Vec",
+    );
+    env.defn(
+        "metadata",
+        metadata,
+        "Str",
+        "a Map of the :size, :mtime in seconds since 1970 and :is-dir of a path",
+    );
+    env.defn("path-join", path_join, "Str ...", "join paths with the separator");
+    env.defn("basename", basename, "Str", "the last component of a path");
+    env.defn("dirname", dirname, "Str", "a path without its last component");
+    env.defn(
+        "extension",
+        extension,
+        "Str",
+        "the extension of a path without the dot, or nil",
+    );
+    env.defn(
+        "absolute",
+        absolute,
+        "Str",
+        "a path made absolute against the current directory",
+    );
+}
+
+fn read_file(args: &[RVal], env: &mut REnv) -> RVal {
+    with_path(args, env, |path| match fs::read_to_string(path) {
+        Ok(s) => RStr(s),
+        Err(e) => io_error("read-file", path, e),
+    })
+}
+
+// writes the second argument to the file named by the first
+fn write_with(op: &str, append: bool, args: &[RVal], env: &mut REnv) -> RVal {
+    let (path, contents) = match args {
+        [x, y] => match (path_arg(x, env), eval(y, env)) {
+            (Err(e), _) | (_, e @ _RErr(_)) => return e,
+            (Ok(path), _RStr(s)) => (path, s.to_string()),
+            (Ok(path), v) => (path, v.to_string()),
+        },
+        _ => return RErrExpected!("(Str Any)", RLstArgs![args].variant()),
+    };
+    let res = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(&path)
+        .and_then(|mut f| f.write_all(contents.as_bytes()));
+    match res {
+        Ok(()) => RLstArgs![[]],
+        Err(e) => io_error(op, &path, e),
+    }
+}
+
+fn write_file(args: &[RVal], env: &mut REnv) -> RVal {
+    write_with("write-file", false, args, env)
+}
+
+fn append_file(args: &[RVal], env: &mut REnv) -> RVal {
+    write_with("append-file", true, args, env)
+}
+
+fn file_exists(args: &[RVal], env: &mut REnv) -> RVal {
+    with_path(args, env, |path| RBool(Path::new(path).exists()))
+}
+
+fn delete_file(args: &[RVal], env: &mut REnv) -> RVal {
+    with_path(args, env, |path| match fs::remove_file(path) {
+        Ok(()) => RLstArgs![[]],
+        Err(e) => io_error("delete-file", path, e),
+    })
+}
+
+fn list_dir(args: &[RVal], env: &mut REnv) -> RVal {
+    with_path(args, env, |path| match entries(Path::new(path)) {
+        Ok(paths) => {
+            let names: Vec<RVal> = paths
+                .iter()
+                .filter_map(|p| p.file_name())
+                .map(|n| RStr(n.to_string_lossy().to_string()))
+                .collect();
+            RVecArgs!(names)
+        }
+        Err(e) => io_error("list-dir", path, e),
+    })
+}
+
+fn make_dir(args: &[RVal], env: &mut REnv) -> RVal {
+    with_path(args, env, |path| match fs::create_dir_all(path) {
+        Ok(()) => RLstArgs![[]],
+        Err(e) => io_error("make-dir", path, e),
+    })
+}
+
+fn walk_dir(args: &[RVal], env: &mut REnv) -> RVal {
+    with_path(args, env, |path| {
+        let mut found = vec![];
+        match walk(Path::new(path), &mut found) {
+            Ok(()) => RVecArgs!(found.iter().map(|p| path_str(p)).collect::<Vec<RVal>>()),
+            Err(e) => io_error("walk-dir", path, e),
+        }
+    })
+}
+
+fn glob(args: &[RVal], env: &mut REnv) -> RVal {
+    with_path(args, env, |pattern| {
+        let found: Vec<RVal> = glob_paths(pattern).iter().map(|p| path_str(p)).collect();
+        RVecArgs!(found)
+    })
+}
+
+fn metadata(args: &[RVal], env: &mut REnv) -> RVal {
+    with_path(args, env, |path| match fs::metadata(path) {
+        Ok(m) => {
+            let mtime = m
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs() as i64);
+            RMapArgs!([
+                RSym(":size"),
+                RInt(m.len() as i64),
+                RSym(":mtime"),
+                RInt(mtime),
+                RSym(":is-dir"),
+                RBool(m.is_dir()),
+            ])
+        }
+        Err(e) => io_error("metadata", path, e),
+    })
+}
+
+fn path_join(args: &[RVal], env: &mut REnv) -> RVal {
+    let mut joined = PathBuf::new();
+    for x in args {
+        match path_arg(x, env) {
+            Ok(path) => joined.push(path),
+            Err(e) => return e,
+        }
+    }
+    path_str(&joined)
+}
+
+fn basename(args: &[RVal], env: &mut REnv) -> RVal {
+    with_path(args, env, |path| match Path::new(path).file_name() {
+        Some(name) => RStr(name.to_string_lossy().to_string()),
+        None => RStr(""),
+    })
+}
+
+fn dirname(args: &[RVal], env: &mut REnv) -> RVal {
+    with_path(args, env, |path| match Path::new(path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => path_str(dir),
+        Some(_) => RStr("."),
+        None => RStr(path),
+    })
+}
+
+fn extension(args: &[RVal], env: &mut REnv) -> RVal {
+    with_path(args, env, |path| match Path::new(path).extension() {
+        Some(ext) => RStr(ext.to_string_lossy().to_string()),
+        None => RNil,
+    })
+}
+
+fn absolute(args: &[RVal], env: &mut REnv) -> RVal {
+    with_path(args, env, |path| match path::absolute(path) {
+        Ok(abs) => path_str(&abs),
+        Err(e) => io_error("absolute", path, e),
+    })
+}
@@ -0,0 +1,131 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use crate::risp::{body_args, is_comment, read_all, strip_shebang, tokenize_source, RVal};
+
+/******************************************************************************
+** @source formatter
+******************************************************************************/
+
+struct Frame {
+    open: char,
+    col: usize,
+    head: Option<String>,
+    first_arg: Option<usize>,
+    elems: usize,
+    broken: bool,
+}
+
+impl Frame {
+    // column the next line inside this form starts at
+    fn indent(&self) -> usize {
+        if self.open != '(' {
+            return self.col + 1;
+        }
+        match &self.head {
+            Some(h) if body_args(h).is_some() || h.starts_with("def") => self.col + 2,
+            _ => match self.first_arg {
+                Some(c) => c,
+                None => self.col + 1,
+            },
+        }
+    }
+}
+
+fn is_open(tok: &str) -> bool {
+    tok == "(" || tok == "[" || tok == "{"
+}
+
+fn is_close(tok: &str) -> bool {
+    tok == ")" || tok == "]" || tok == "}"
+}
+
+fn column(out: &str) -> usize {
+    match out.rfind('\n') {
+        Some(i) => out[i + 1..].chars().count(),
+        None => out.chars().count(),
+    }
+}
+
+/// Re-indents risp source following the usual Lisp conventions: line breaks
+/// chosen by the author are kept (at most one blank line in a row), closing
+/// brackets are pulled up onto the line they close, and every line is
+/// indented relative to the form that contains it. Source that does not read
+/// is left alone and its parse error returned.
+pub fn format_source<S>(src: S) -> Result<String, RVal>
+where
+    S: Into<String>,
+{
+    let src = src.into();
+    // a #! line is not risp, keep it as it is
+    let body = strip_shebang(&src);
+    read_all(body)?;
+    let shebang = match &src[..src.len() - body.len()] {
+        "" => String::new(),
+        line => format!("{}\n", line),
+    };
+    let mut out = String::new();
+    let mut stack: Vec<Frame> = vec![];
+    let mut last = String::new();
+    for (tok, breaks) in tokenize_source(body) {
+        let after_comment = last.starts_with(';');
+        let after_prefix = last == "'"
+            || last == "#'"
+            || last == "#_"
+            || (last == "^" && (tok == "{" || tok.starts_with(':')));
+        if is_close(&tok) {
+            if after_comment {
+                let indent = stack.last().map_or(0, |f| f.indent());
+                out.push('\n');
+                out.push_str(&" ".repeat(indent));
+            }
+            stack.pop();
+            out.push_str(&tok);
+            last = tok;
+            continue;
+        }
+        if !out.is_empty() {
+            if breaks > 0 || after_comment {
+                if let Some(f) = stack.last_mut() {
+                    f.broken = true;
+                }
+                out.push('\n');
+                if breaks > 1 {
+                    out.push('\n');
+                }
+                let indent = stack.last().map_or(0, |f| f.indent());
+                out.push_str(&" ".repeat(indent));
+            } else if !is_open(&last) && !after_prefix {
+                out.push(' ');
+            }
+        }
+        let col = column(&out);
+        if !is_comment(&tok) && !after_prefix {
+            if let Some(f) = stack.last_mut() {
+                f.elems += 1;
+                if f.elems == 1 && !is_open(&tok) {
+                    f.head = Some(tok.clone());
+                } else if f.elems == 2 && !f.broken {
+                    f.first_arg = Some(col);
+                }
+            }
+        }
+        out.push_str(&tok);
+        if is_open(&tok) {
+            stack.push(Frame {
+                open: tok.chars().next().unwrap(),
+                col,
+                head: None,
+                first_arg: None,
+                elems: 0,
+                broken: false,
+            });
+        }
+        last = tok;
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    Ok(shebang + &out)
+}
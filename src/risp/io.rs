@@ -1,58 +1,90 @@
 extern crate rustyline;
+use rustyline::error::ReadlineError;
 use rustyline::Editor;
 
-use crate::risp::{eval, REnv, RErr, RStr, RVal, RVal::*};
+use std::io::{self, IsTerminal};
+
+use crate::risp::{eval, read_stdin_line, REnv, RErr, RStr, RVal, RVal::*};
 
 pub fn load_io(env: &mut REnv) {
-    env.def("read", RBfn(read));
-    env.def("write", RBfn(write));
-    env.def("load", RBfn(load));
+    env.defn(
+        "read",
+        read,
+        "Str?",
+        "read a line from stdin, or nil at the end, showing an optional prompt\n\
+         when stdin is a terminal",
+    );
+    env.defn("write", write, "Any ...", "print values, strings without quotes");
+    env.defn("load", load, "(Sym | Str)", "evaluate the file at a path");
+    env.defn("exit", exit, "Int?", "end the program with an exit status, 0 by default");
 }
 
-fn read(args: &[RVal], _env: &mut REnv) -> RVal {
+// the prompt only makes sense in a terminal, lines piped in are read as they
+// are
+fn read(args: &[RVal], env: &mut REnv) -> RVal {
+    let prompt = match args {
+        [] => String::new(),
+        [x] => match eval(x, env) {
+            _RStr(s) => s.to_string(),
+            e @ _RErr(_) => return e,
+            v => return RErrExpected!("(Str)", v.variant()),
+        },
+        _ => return RErrExpected!("(Str)", RLstArgs![args].variant()),
+    };
+    if !io::stdin().is_terminal() {
+        return match read_stdin_line() {
+            Ok(line) => line.map_or(RNil, RStr),
+            Err(e) => RErr(format!("read: stdin: {}", e.kind())),
+        };
+    }
     let mut rl = Editor::<()>::new();
-    if args.is_empty() {
-        match rl.readline("") {
-            Ok(line) => RStr(line.as_str()),
-            _ => RErr("could not read line"),
-        }
-    } else if args.len() == 1 {
-        match &args[0] {
-            _RStr(s) => match rl.readline(&s[..]) {
-                Ok(line) => RStr(line.as_str()),
-                _ => RErr("could not read line"),
-            },
-            _ => RErrExpected!("(Str)"),
-        }
-    } else {
-        RErrExpected!("(Str)", RLstArgs![args].variant())
+    match rl.readline(&prompt) {
+        Ok(line) => RStr(line),
+        Err(ReadlineError::Eof) => RNil,
+        Err(_) => RErr("could not read line"),
     }
 }
 
 fn write(args: &[RVal], env: &mut REnv) -> RVal {
     for v in args.iter() {
-        match &v {
-            _RStr(s) => print!("{}", s),
+        let res = match &v {
+            _RStr(s) => env.write_out(s),
             _RSym(_) => {
-                let new_v = eval(&v, env);
+                let new_v = eval(v, env);
                 match &new_v {
                     _RErr(_) => return RErrUnboundSymbol!(v),
-                    _RStr(s) => print!("{}", s),
-                    _ => print!("{}", new_v),
+                    _RStr(s) => env.write_out(s),
+                    _ => env.write_out(&new_v.to_string()),
                 }
             }
-            _ => print!("{}", v),
+            _ => env.write_out(&v.to_string()),
+        };
+        if let Err(e) = res {
+            return e;
         }
     }
-    RLstArgs![vec![]]
+    RLstArgs![[]]
 }
 
 fn load(args: &[RVal], env: &mut REnv) -> RVal {
     match args.len() {
         1 => match &args[0] {
-            _RSym(path) => env.load(&path[..]),
-            _ => RErrExpected!("(Sym)", RLstArgs![args].variant()),
+            _RSym(path) | _RStr(path) => env.load(&path[..]),
+            _ => RErrExpected!("(Sym | Str)", RLstArgs![args].variant()),
         },
-        _ => RErrExpected!("(Sym)", RLstArgs![args].variant()),
+        _ => RErrExpected!("(Sym | Str)", RLstArgs![args].variant()),
     }
 }
+
+fn exit(args: &[RVal], env: &mut REnv) -> RVal {
+    let code = match args {
+        [] => 0,
+        [x] => match eval(x, env) {
+            RInt(n) => n as i32,
+            v => return RErrExpected!("(Int?)", v.variant()),
+        },
+        _ => return RErrExpected!("(Int?)", RLstArgs![args].variant()),
+    };
+    env.exit.set(Some(code));
+    RErr("exit")
+}
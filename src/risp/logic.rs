@@ -4,6 +4,7 @@
 
 use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
+use std::rc::Rc;
 
 use crate::risp::{eval, REnv, RErr, RVal, RVal::*};
 
@@ -42,6 +43,7 @@ impl PartialEq for RVal {
             (RVec(a), RVec(b)) => a.eq(b),
             (RLst(a), RLst(b)) => a.eq(b),
             (RMap(a), RMap(b)) => a.eq(b),
+            (RPort(a), RPort(b)) => Rc::ptr_eq(a, b),
             _ => false,
         }
     }
@@ -68,18 +70,18 @@ impl PartialOrd for RVal {
 ******************************************************************************/
 
 pub fn load_logic(env: &mut REnv) {
-    env.def("not", RBfn(not));
-    env.def("and", RBfn(and));
-    env.def("or", RBfn(or));
-    env.def("!", RBfn(not));
-    env.def("&&", RBfn(and));
-    env.def("||", RBfn(or));
-    env.def("=", RBfn(eq));
-    env.def("!=", RBfn(ne));
-    env.def("<", RBfn(lt));
-    env.def("<=", RBfn(le));
-    env.def(">", RBfn(gt));
-    env.def(">=", RBfn(ge));
+    env.defn("not", not, "Bool", "logical not");
+    env.defn("and", and, "Bool ...", "true if every value is true");
+    env.defn("or", or, "Bool ...", "true if any value is true");
+    env.defn("!", not, "Bool", "logical not");
+    env.defn("&&", and, "Bool ...", "true if every value is true");
+    env.defn("||", or, "Bool ...", "true if any value is true");
+    env.defn("=", eq, "Any Any ...", "true if all values are equal");
+    env.defn("!=", ne, "Any Any ...", "true if every value differs from the next one");
+    env.defn("<", lt, "Any Any ...", "true if the values are strictly increasing");
+    env.defn("<=", le, "Any Any ...", "true if the values are increasing");
+    env.defn(">", gt, "Any Any ...", "true if the values are strictly decreasing");
+    env.defn(">=", ge, "Any Any ...", "true if the values are decreasing");
 }
 
 macro_rules! rval_logic {
@@ -131,7 +133,7 @@ fn and(args: &[RVal], env: &mut REnv) -> RVal {
     } else {
         let mut acc = true;
         for e in args.iter() {
-            match eval(&e, env) {
+            match eval(e, env) {
                 RBool(b) => {
                     acc = acc && b;
                 }
@@ -147,12 +149,12 @@ fn or(args: &[RVal], env: &mut REnv) -> RVal {
         RErrExpected!("(Bool ...)")
     } else {
         for e in args.iter() {
-            match eval(&e, env) {
+            match eval(e, env) {
                 RBool(b) => {
                     if b {
                         return RBool(true);
                     } else {
-                        RLstArgs![vec![]]
+                        RLstArgs![[]]
                     }
                 }
                 _ => return RErrExpected!("(Bool)", e.variant()),
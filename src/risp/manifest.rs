@@ -0,0 +1,236 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/******************************************************************************
+** @project manifest
+******************************************************************************/
+
+pub const MANIFEST: &str = "risp.toml";
+
+// a project and every project it depends on, found through risp.toml files:
+//
+//   [package]
+//   name = "app"
+//   entry = "src/main.risp"
+//   source-dirs = ["src"]
+//
+//   [dependencies]
+//   util = { path = "../util" }
+#[derive(Clone)]
+pub struct Project {
+    // the package name, or the name a dependency was declared with
+    pub name: String,
+    pub root: PathBuf,
+    pub entry: PathBuf,
+    pub source_dirs: Vec<PathBuf>,
+}
+
+enum Value {
+    Str(String),
+    List(Vec<String>),
+    Table(Vec<(String, String)>),
+}
+
+// the subset of TOML manifests use: [sections] of key = value lines, where
+// values are strings, lists of strings or inline tables of strings, and lists
+// may span several lines
+fn parse_toml(src: &str) -> Result<Vec<(String, String, Value)>, String> {
+    let mut section = String::new();
+    let mut entries = vec![];
+    let mut lines = src.lines().enumerate();
+    while let Some((n, line)) = lines.next() {
+        let err = |msg: &str| format!("{}: {}", n + 1, msg);
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            match name.strip_suffix(']') {
+                Some(name) => section = name.trim().to_string(),
+                None => return Err(err("expected ]")),
+            }
+            continue;
+        }
+        let (key, rest) = match line.split_once('=') {
+            Some((k, v)) => (k.trim().to_string(), v.trim()),
+            None => return Err(err("expected key = value")),
+        };
+        // a value with open brackets continues on the following lines
+        let mut text = rest.to_string();
+        while depth(&text) > 0 {
+            match lines.next() {
+                Some((_, next)) => {
+                    text.push(' ');
+                    text.push_str(strip_comment(next).trim());
+                }
+                None => return Err(err("unterminated value")),
+            }
+        }
+        let (value, rest) = parse_value(&text).map_err(err)?;
+        if !rest.trim().is_empty() {
+            return Err(err("unexpected text after value"));
+        }
+        entries.push((section.clone(), key, value));
+    }
+    Ok(entries)
+}
+
+// a line up to a # outside of strings
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
+
+// the number of brackets left open outside of strings
+fn depth(s: &str) -> i32 {
+    let (mut depth, mut quoted) = (0, false);
+    for c in s.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '[' | '{' if !quoted => depth += 1,
+            ']' | '}' if !quoted => depth -= 1,
+            _ => (),
+        }
+    }
+    depth
+}
+
+fn parse_string(s: &str) -> Result<(String, &str), &'static str> {
+    let s = s.trim_start();
+    let body = s.strip_prefix('"').ok_or("expected a string")?;
+    let end = body.find('"').ok_or("unterminated string")?;
+    Ok((body[..end].to_string(), &body[end + 1..]))
+}
+
+fn parse_value(s: &str) -> Result<(Value, &str), &'static str> {
+    let s = s.trim_start();
+    let (open, close) = match s.chars().next() {
+        Some('[') => ('[', ']'),
+        Some('{') => ('{', '}'),
+        _ => return parse_string(s).map(|(v, rest)| (Value::Str(v), rest)),
+    };
+    let mut rest = s[1..].trim_start();
+    let (mut list, mut table) = (vec![], vec![]);
+    while !rest.starts_with(close) {
+        if open == '[' {
+            let (v, r) = parse_string(rest)?;
+            list.push(v);
+            rest = r;
+        } else {
+            let (k, r) = split_key(rest)?;
+            let (v, r) = parse_string(r)?;
+            table.push((k, v));
+            rest = r;
+        }
+        rest = rest.trim_start();
+        if let Some(r) = rest.strip_prefix(',') {
+            rest = r.trim_start();
+        } else if !rest.starts_with(close) {
+            return Err("expected , or the end of the value");
+        }
+    }
+    let value = if open == '[' {
+        Value::List(list)
+    } else {
+        Value::Table(table)
+    };
+    Ok((value, &rest[1..]))
+}
+
+// key = in an inline table
+fn split_key(s: &str) -> Result<(String, &str), &'static str> {
+    match s.split_once('=') {
+        Some((k, rest)) => Ok((k.trim().to_string(), rest)),
+        None => Err("expected key = value"),
+    }
+}
+
+// the directory holding risp.toml in dir or one of its ancestors
+pub fn find_manifest(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|d| d.join(MANIFEST).is_file())
+        .map(Path::to_path_buf)
+}
+
+// a path as it is shown in messages, relative to the current directory when
+// it is under it
+pub fn relative(path: &Path) -> String {
+    let cwd = env::current_dir().and_then(fs::canonicalize);
+    match cwd.as_ref().map(|cwd| path.strip_prefix(cwd)) {
+        Ok(Ok(rel)) => rel.display().to_string(),
+        _ => path.display().to_string(),
+    }
+}
+
+// the project at root, followed by its dependencies and theirs
+pub fn read_project(root: &Path) -> Result<Vec<Project>, String> {
+    let mut projects = vec![];
+    read_into(root, None, &mut projects)?;
+    Ok(projects)
+}
+
+fn read_into(root: &Path, name: Option<&str>, projects: &mut Vec<Project>) -> Result<(), String> {
+    let root = fs::canonicalize(root).unwrap_or(root.to_path_buf());
+    if projects.iter().any(|p| p.root == root) {
+        return Ok(());
+    }
+    let path = root.join(MANIFEST);
+    let src = fs::read_to_string(&path)
+        .map_err(|e| format!("could not read {}: {}", relative(&path), e))?;
+    let entries = parse_toml(&src).map_err(|e| format!("{}:{}", relative(&path), e))?;
+    let mut project = Project {
+        name: name.unwrap_or("").to_string(),
+        root: root.clone(),
+        entry: root.join("src/main.risp"),
+        source_dirs: vec![root.join("src")],
+    };
+    let mut deps = vec![];
+    for (section, key, value) in entries {
+        match (&section[..], &key[..], value) {
+            ("package", "name", Value::Str(s)) => {
+                if name.is_none() {
+                    project.name = s
+                }
+            }
+            ("package", "entry", Value::Str(s)) => project.entry = root.join(s),
+            ("package", "source-dirs", Value::List(dirs)) => {
+                project.source_dirs = dirs.iter().map(|d| root.join(d)).collect()
+            }
+            ("dependencies", dep, Value::Table(t)) => match t.iter().find(|(k, _)| k == "path") {
+                Some((_, p)) => deps.push((dep.to_string(), root.join(p))),
+                None => {
+                    return Err(format!(
+                        "{}: dependency {} needs a path",
+                        relative(&path),
+                        dep
+                    ))
+                }
+            },
+            (section, key, _) => {
+                return Err(format!(
+                    "{}: unexpected value for {} in [{}]",
+                    relative(&path),
+                    key,
+                    section
+                ))
+            }
+        }
+    }
+    projects.push(project);
+    for (dep, dir) in deps {
+        read_into(&dir, Some(&dep), projects)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,209 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use fnv::FnvHashMap;
+
+use std::rc::{Rc, Weak};
+
+use crate::risp::{eval, REnv, RErr, RLambda, RVal, RVal::*};
+
+/******************************************************************************
+** @metadata
+******************************************************************************/
+
+// (with-meta form meta), as read from ^meta form
+pub fn split_meta(x: &RVal) -> Option<(&RVal, &RVal)> {
+    match x {
+        RLst(vs) => match &vs[..] {
+            [_RSym(s), form, m] if &s[..] == "with-meta" => Some((form, m)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// keys in b take precedence over keys in a
+pub fn merge_meta(a: &RVal, b: &RVal) -> RVal {
+    match (a, b) {
+        (RMap(x), RMap(y)) => {
+            let kvs = x.iter().chain(y.iter());
+            RMap(Rc::new(kvs.map(|(k, v)| (k.clone(), v.clone())).collect()))
+        }
+        (RNil, _) => b.clone(),
+        _ => a.clone(),
+    }
+}
+
+// lists, vectors, maps and symbols have no room for metadata, so it is kept
+// here by the address of their contents; the weak reference keeps that
+// address from being reused while the entry exists
+#[derive(Clone)]
+pub struct ValueMeta {
+    entries: FnvHashMap<usize, (Held, RVal)>,
+    // entries of dropped values are swept out when there are this many
+    limit: usize,
+}
+
+#[derive(Clone)]
+enum Held {
+    Seq(Weak<Vec<RVal>>),
+    Map(Weak<FnvHashMap<RVal, RVal>>),
+    Sym(Weak<String>),
+}
+
+impl Held {
+    fn is_alive(&self) -> bool {
+        match self {
+            Held::Seq(w) => w.strong_count() > 0,
+            Held::Map(w) => w.strong_count() > 0,
+            Held::Sym(w) => w.strong_count() > 0,
+        }
+    }
+}
+
+// the address metadata of a value is kept under, if it can have any
+fn address(v: &RVal) -> Option<usize> {
+    match v {
+        RLst(vs) | RVec(vs) => Some(Rc::as_ptr(vs) as usize),
+        RMap(hm) => Some(Rc::as_ptr(hm) as usize),
+        _RSym(s) => Some(Rc::as_ptr(s) as usize),
+        _ => None,
+    }
+}
+
+impl ValueMeta {
+    pub fn new() -> Self {
+        ValueMeta {
+            entries: FnvHashMap::default(),
+            limit: 64,
+        }
+    }
+    pub fn get(&self, v: &RVal) -> RVal {
+        match address(v).and_then(|a| self.entries.get(&a)) {
+            Some((_, m)) => m.clone(),
+            None => RNil,
+        }
+    }
+    // a copy of v carrying the metadata m
+    fn attach(&mut self, v: &RVal, m: RVal) -> RVal {
+        let (copy, held) = match v {
+            RLst(vs) => {
+                let vs = Rc::new((**vs).clone());
+                (RLst(vs.clone()), Held::Seq(Rc::downgrade(&vs)))
+            }
+            RVec(vs) => {
+                let vs = Rc::new((**vs).clone());
+                (RVec(vs.clone()), Held::Seq(Rc::downgrade(&vs)))
+            }
+            RMap(hm) => {
+                let hm = Rc::new((**hm).clone());
+                (RMap(hm.clone()), Held::Map(Rc::downgrade(&hm)))
+            }
+            _RSym(s) => {
+                let s = Rc::new((**s).clone());
+                (_RSym(s.clone()), Held::Sym(Rc::downgrade(&s)))
+            }
+            _ => return RErr(format!("cannot attach metadata to {}", v.variant())),
+        };
+        if self.entries.len() >= self.limit {
+            self.entries.retain(|_, (held, _)| held.is_alive());
+            self.limit = (self.entries.len() * 2).max(64);
+        }
+        let m = merge_meta(&self.get(v), &m);
+        if let Some(a) = address(&copy) {
+            self.entries.insert(a, (held, m));
+        }
+        copy
+    }
+}
+
+// the metadata a value carries
+pub fn meta_of(v: &RVal, env: &REnv) -> RVal {
+    match v {
+        RLfn(l) => (*l.meta).clone(),
+        _ => env.value_meta.borrow().get(v),
+    }
+}
+
+// functions carry their own metadata, lists, vectors, maps and symbols get a
+// copy that carries it
+pub fn attach_meta(val: RVal, m: RVal, env: &REnv) -> RVal {
+    match (val, m) {
+        (v @ _RErr(_), _) | (_, v @ _RErr(_)) => v,
+        (RLfn(l), m @ RMap(_)) => RLfn(Rc::new(RLambda {
+            params: l.params.clone(),
+            body: l.body.clone(),
+            meta: Rc::new(merge_meta(&l.meta, &m)),
+            module: l.module.clone(),
+        })),
+        (v, m @ RMap(_)) => env.value_meta.borrow_mut().attach(&v, m),
+        (_, m) => RErrExpected!("Map", m.variant()),
+    }
+}
+
+// a quoted form with the metadata read with ^ attached to the data it marks,
+// or None if it has none
+pub fn quote_meta(x: &RVal, env: &REnv) -> Option<RVal> {
+    if let Some((form, m)) = split_meta(x) {
+        let form = quote_meta(form, env).unwrap_or_else(|| form.clone());
+        return Some(attach_meta(form, m.clone(), env));
+    }
+    let (vs, rebuild): (_, fn(Vec<RVal>) -> RVal) = match x {
+        RLst(vs) => (vs, |vs| RLst(Rc::new(vs))),
+        RVec(vs) => (vs, |vs| RVec(Rc::new(vs))),
+        _ => return None,
+    };
+    let quoted: Vec<Option<RVal>> = vs.iter().map(|v| quote_meta(v, env)).collect();
+    if quoted.iter().all(Option::is_none) {
+        return None;
+    }
+    let vs = vs
+        .iter()
+        .zip(quoted)
+        .map(|(v, q)| q.unwrap_or_else(|| v.clone()))
+        .collect();
+    Some(rebuild(vs))
+}
+
+/******************************************************************************
+** @metadata into environment
+******************************************************************************/
+
+pub fn load_meta(env: &mut REnv) {
+    env.defn("meta", meta, "Any", "get the metadata of a symbol or a value");
+    env.defn(
+        "with-meta",
+        with_meta,
+        "Any Map",
+        "a function, list, vector, map or symbol carrying metadata",
+    );
+}
+
+fn meta(args: &[RVal], env: &mut REnv) -> RVal {
+    match args {
+        [_RSym(s)] if !s.starts_with(':') => {
+            let bound = env.meta.get(&s[..]).cloned().unwrap_or(RNil);
+            match env.get(&s[..]) {
+                Some(v) => merge_meta(&meta_of(&v, env), &bound),
+                None => RErrUnboundSymbol!(s),
+            }
+        }
+        [x] => match eval(x, env) {
+            v @ _RErr(_) => v,
+            v => meta_of(&v, env),
+        },
+        _ => RErrExpected!("(Any)", RLstArgs![args].variant()),
+    }
+}
+
+fn with_meta(args: &[RVal], env: &mut REnv) -> RVal {
+    match args {
+        [form, m] => {
+            let val = eval(form, env);
+            let m = eval(m, env);
+            attach_meta(val, m, env)
+        }
+        _ => RErrExpected!("(Any Map)", RLstArgs![args].variant()),
+    }
+}
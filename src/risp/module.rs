@@ -0,0 +1,309 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use fnv::{FnvHashMap, FnvHashSet};
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::risp::{
+    resolve, stdlib_source, strip_shebang, Project, REnv, RErr, RLambda, RSym, RVal, RVal::*,
+};
+
+/******************************************************************************
+** @modules
+******************************************************************************/
+
+// directories searched for modules imported by name, separated like PATH
+pub const RISP_PATH: &str = "RISP_PATH";
+
+#[derive(Clone)]
+pub struct Module {
+    pub name: String,
+    pub exports: Vec<(String, RVal)>,
+}
+
+// a module being imported, until its file has been evaluated
+struct Loading {
+    key: String,
+    name: String,
+    exports: Option<Vec<String>>,
+}
+
+pub struct Modules {
+    // every module imported so far, by canonical path
+    cache: FnvHashMap<String, Module>,
+    loading: Vec<Loading>,
+    // files being loaded or imported, relative paths are resolved against the
+    // last one
+    files: Vec<PathBuf>,
+    // whether modules start with the standard library, off with --no-prelude
+    pub prelude: bool,
+    // the project in the current directory and its dependencies, if any
+    pub projects: Vec<Project>,
+}
+
+impl Modules {
+    pub fn new() -> Self {
+        Modules {
+            cache: FnvHashMap::default(),
+            loading: vec![],
+            files: vec![],
+            prelude: true,
+            projects: vec![],
+        }
+    }
+}
+
+// the file a path given to load or import refers to: ./ and ../ paths are
+// relative to the file being loaded, other relative paths are looked up next
+// to it, in the source directories of the project, in the current directory
+// and in RISP_PATH, and dep or dep/path in the project's dependency dep
+pub fn find_file(spec: &str, env: &REnv) -> Option<PathBuf> {
+    let modules = env.modules.borrow();
+    let here = modules
+        .files
+        .last()
+        .and_then(|f| f.parent().map(Path::to_path_buf));
+    let path = Path::new(spec);
+    let mut dirs = vec![];
+    if path.is_absolute() {
+        dirs.push(PathBuf::new());
+    } else if spec.starts_with("./") || spec.starts_with("../") {
+        dirs.push(here.unwrap_or_default());
+    } else {
+        dirs.extend(here);
+        if let Some(p) = modules.projects.first() {
+            dirs.extend(p.source_dirs.iter().cloned());
+        }
+        dirs.push(PathBuf::new());
+        if let Some(paths) = env::var_os(RISP_PATH) {
+            dirs.extend(env::split_paths(&paths));
+        }
+    }
+    let found = dirs
+        .iter()
+        .filter_map(|d| resolve(&d.join(path).to_string_lossy()))
+        .map(PathBuf::from)
+        .next();
+    found.or_else(|| {
+        modules.projects.iter().find_map(|p| {
+            if spec == p.name {
+                return resolve(&p.entry.to_string_lossy()).map(PathBuf::from);
+            }
+            let rest = spec.strip_prefix(&p.name)?.strip_prefix('/')?;
+            p.source_dirs
+                .iter()
+                .find_map(|d| resolve(&d.join(rest).to_string_lossy()))
+                .map(PathBuf::from)
+        })
+    })
+}
+
+// the source of the file or standard library module spec refers to, and the
+// key it is cached under
+pub fn read_module(spec: &str, env: &REnv) -> Result<(String, String, Option<PathBuf>), RVal> {
+    if let Some(src) = stdlib_source(spec) {
+        let key = spec.trim_end_matches(".risp").trim_end_matches(".rs");
+        return Ok((key.to_string(), src, None));
+    }
+    let path = match find_file(spec, env) {
+        Some(path) => path,
+        None => return Err(RErr(format!("could not load {}", spec))),
+    };
+    let path = fs::canonicalize(&path).unwrap_or(path);
+    match fs::read_to_string(&path) {
+        Ok(src) => {
+            let src = strip_shebang(&src).to_string();
+            Ok((path.to_string_lossy().to_string(), src, Some(path)))
+        }
+        Err(e) => Err(RErr(format!("could not load {}: {}", spec, e))),
+    }
+}
+
+// evaluates src with path as the file relative paths are resolved against
+pub fn eval_file(src: String, path: Option<PathBuf>, env: &mut REnv) -> RVal {
+    let pushed = path.is_some();
+    if let Some(path) = path {
+        env.modules.borrow_mut().files.push(path);
+    }
+    let res = env.eval_source(src);
+    if pushed {
+        env.modules.borrow_mut().files.pop();
+    }
+    res
+}
+
+// evaluates a module in a namespace of its own, once
+fn import_module(spec: &str, env: &REnv) -> Result<Module, RVal> {
+    let (key, src, path) = read_module(spec, env)?;
+    {
+        let modules = env.modules.borrow();
+        if let Some(m) = modules.cache.get(&key) {
+            return Ok(m.clone());
+        }
+        if let Some(i) = modules.loading.iter().position(|l| l.key == key) {
+            let mut cycle: Vec<&str> = modules.loading[i..].iter().map(|l| &l.name[..]).collect();
+            cycle.push(&modules.loading[i].name[..]);
+            return Err(RErr(format!("import cycle: {}", cycle.join(" -> "))));
+        }
+    }
+    let mut menv = env.fresh();
+    if env.modules.borrow().prelude {
+        if let e @ _RErr(_) = menv.load("stdlib/prelude") {
+            return Err(e);
+        }
+    }
+    let baseline: FnvHashSet<String> = menv.symbols.keys().cloned().collect();
+    let name = Path::new(&key)
+        .file_stem()
+        .map_or(key.clone(), |s| s.to_string_lossy().to_string());
+    env.modules.borrow_mut().loading.push(Loading {
+        key: key.clone(),
+        name,
+        exports: None,
+    });
+    let res = eval_file(src, path, &mut menv);
+    let loading = env.modules.borrow_mut().loading.pop().unwrap();
+    if let e @ _RErr(_) = res {
+        return Err(e);
+    }
+    let names = match loading.exports {
+        Some(names) => names,
+        None => {
+            let mut names: Vec<String> = menv
+                .symbols
+                .keys()
+                .filter(|k| !baseline.contains(*k))
+                .cloned()
+                .collect();
+            names.sort();
+            names
+        }
+    };
+    // functions keep seeing the module's bindings when called from elsewhere
+    let scope = Rc::new(menv.symbols.clone());
+    let mut exports = vec![];
+    for n in names {
+        let val = match menv.symbols.get(&n) {
+            Some(RLfn(l)) => RLfn(Rc::new(RLambda {
+                params: l.params.clone(),
+                body: l.body.clone(),
+                meta: l.meta.clone(),
+                module: Some(scope.clone()),
+            })),
+            Some(v) => v.clone(),
+            None => {
+                return Err(RErr(format!(
+                    "{} is exported by {} but not defined",
+                    n, loading.name
+                )))
+            }
+        };
+        exports.push((n, val));
+    }
+    let module = Module {
+        name: loading.name,
+        exports,
+    };
+    env.modules
+        .borrow_mut()
+        .cache
+        .insert(key, module.clone());
+    Ok(module)
+}
+
+/******************************************************************************
+** @modules into environment
+******************************************************************************/
+
+pub fn load_module(env: &mut REnv) {
+    env.defn(
+        "module",
+        module,
+        "Sym (export Sym ...)?",
+        "name the module a file defines and the bindings it exports",
+    );
+    env.defn(
+        "import",
+        import,
+        "(Str | Sym) (:as Sym | :only (Sym ...))?",
+        "bind the exports of a module as name/export, alias/export or\n\
+         only the given ones unqualified",
+    );
+}
+
+fn module(args: &[RVal], env: &mut REnv) -> RVal {
+    let (name, exports) = match args {
+        [_RSym(name)] => (name, None),
+        [_RSym(name), RLst(vs)] => match &vs[..] {
+            [_RSym(e), names @ ..] if &e[..] == "export" => {
+                let mut exports = vec![];
+                for n in names {
+                    match n {
+                        _RSym(s) => exports.push(s.to_string()),
+                        v => return RErrExpected!("Sym", v.variant()),
+                    }
+                }
+                (name, Some(exports))
+            }
+            _ => return RErrExpected!("(export Sym ...)", args[1].variant()),
+        },
+        _ => return RErrExpected!("(Sym (export Sym ...)?)", RLstArgs![args].variant()),
+    };
+    // only means something in a file being imported
+    if let Some(loading) = env.modules.borrow_mut().loading.last_mut() {
+        loading.name = name.to_string();
+        loading.exports = exports;
+    }
+    RSym(&name[..])
+}
+
+fn import(args: &[RVal], env: &mut REnv) -> RVal {
+    let spec = match args.first() {
+        Some(_RStr(s)) | Some(_RSym(s)) => s.to_string(),
+        _ => return RErrExpected!("(Str | Sym)", RLstArgs![args].variant()),
+    };
+    let module = match import_module(&spec, env) {
+        Ok(module) => module,
+        Err(e) => return e,
+    };
+    let qualified = |prefix: &str| -> Vec<(String, RVal)> {
+        module
+            .exports
+            .iter()
+            .map(|(n, v)| (format!("{}/{}", prefix, n), v.clone()))
+            .collect()
+    };
+    let bindings = match &args[1..] {
+        [] => qualified(&module.name),
+        [_RSym(k), _RSym(alias)] if &k[..] == ":as" => qualified(alias),
+        [_RSym(k), RLst(names)] if &k[..] == ":only" => {
+            let mut bindings = vec![];
+            for n in names.iter() {
+                let found = match n {
+                    _RSym(s) => module.exports.iter().find(|(e, _)| e == &s[..]),
+                    v => return RErrExpected!("Sym", v.variant()),
+                };
+                match found {
+                    Some(b) => bindings.push(b.clone()),
+                    None => return RErr(format!("{} does not export {}", module.name, n)),
+                }
+            }
+            bindings
+        }
+        _ => {
+            return RErrExpected!(
+                "((Str | Sym) (:as Sym | :only (Sym ...))?)",
+                RLstArgs![args].variant()
+            )
+        }
+    };
+    for (n, v) in bindings {
+        env.def(n, v);
+    }
+    RSym(&module.name[..])
+}
@@ -0,0 +1,120 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::mem;
+use std::rc::Rc;
+
+use crate::risp::{eval, REnv, RErr, RStr, RVal, RVal::*};
+
+/******************************************************************************
+** @output
+******************************************************************************/
+
+// where what a program writes goes, the process's stdout and stderr unless
+// with-output-to-string or with-error-to-string replaces them
+pub struct Output {
+    pub stdout: Box<dyn Write>,
+    pub stderr: Box<dyn Write>,
+}
+
+impl Output {
+    pub fn new() -> Self {
+        Output {
+            stdout: Box::new(io::stdout()),
+            stderr: Box::new(io::stderr()),
+        }
+    }
+}
+
+// a writer keeping what is written in memory, clones share the contents
+#[derive(Clone, Default)]
+pub struct Capture(Rc<RefCell<Vec<u8>>>);
+
+impl Capture {
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).to_string()
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl REnv {
+    pub fn write_out(&self, s: &str) -> Result<(), RVal> {
+        let res = self.output.borrow_mut().stdout.write_all(s.as_bytes());
+        res.map_err(|e| RErr(format!("could not write to stdout: {}", e.kind())))
+    }
+    pub fn write_err(&self, s: &str) -> Result<(), RVal> {
+        let res = self.output.borrow_mut().stderr.write_all(s.as_bytes());
+        res.map_err(|e| RErr(format!("could not write to stderr: {}", e.kind())))
+    }
+    pub fn flush_output(&self) -> Result<(), RVal> {
+        let mut output = self.output.borrow_mut();
+        let res = output.stdout.flush().and(output.stderr.flush());
+        res.map_err(|e| RErr(format!("could not flush output: {}", e.kind())))
+    }
+}
+
+/******************************************************************************
+** @output into environment
+******************************************************************************/
+
+pub fn load_output(env: &mut REnv) {
+    env.defn(
+        "with-output-to-string",
+        with_output_to_string,
+        "Any ...",
+        "evaluate expressions and return what they wrote to stdout as a string",
+    );
+    env.defn(
+        "with-error-to-string",
+        with_error_to_string,
+        "Any ...",
+        "evaluate expressions and return what they wrote to stderr as a string",
+    );
+}
+
+// evaluates body with stdout, or stderr, written to a string
+fn capture(body: &[RVal], env: &mut REnv, stderr: bool) -> RVal {
+    let captured = Capture::default();
+    let replace = |env: &REnv, w: Box<dyn Write>| {
+        let mut output = env.output.borrow_mut();
+        let sink = if stderr {
+            &mut output.stderr
+        } else {
+            &mut output.stdout
+        };
+        mem::replace(sink, w)
+    };
+    let previous = replace(env, Box::new(captured.clone()));
+    let mut res = RNil;
+    for x in body {
+        res = eval(x, env);
+        if let _RErr(_) = res {
+            break;
+        }
+    }
+    replace(env, previous);
+    match res {
+        e @ _RErr(_) => e,
+        _ => RStr(captured.contents()),
+    }
+}
+
+fn with_output_to_string(args: &[RVal], env: &mut REnv) -> RVal {
+    capture(args, env, false)
+}
+
+fn with_error_to_string(args: &[RVal], env: &mut REnv) -> RVal {
+    capture(args, env, true)
+}
@@ -2,11 +2,9 @@
 ** @crates and modules
 ******************************************************************************/
 
-extern crate lazy_static;
-extern crate regex;
-use regex::Regex;
+use std::rc::Rc;
 
-use crate::risp::{RErr, RStr, RSym, RVal, RVal::*};
+use crate::risp::{REnv, RErr, RStr, RSym, RVal, RVal::*};
 
 /******************************************************************************
 ** @lexer
@@ -16,23 +14,165 @@ pub fn tokenize<S>(src: S) -> Vec<String>
 where
     S: Into<String>,
 {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(
-            r#"(?mx)
-        [\s]*(,@|[\[\]{}()`,^@]|"(?:\\.|[^\\"])*"?|;.*|[^\s\[\]{}("`,;)]+)
-        "#
-        )
-        .unwrap();
+    tokenize_source(src)
+        .into_iter()
+        .filter(|(t, _)| !is_comment(t))
+        .map(|(t, _)| t)
+        .collect()
+}
+
+pub fn is_comment(token: &str) -> bool {
+    token.starts_with(';') || token.starts_with("#|")
+}
+
+// a script without its #!/usr/bin/env risp line, so scripts can be
+// executable; only files and programs read from stdin are scripts, so -e and
+// the REPL read it as risp
+pub fn strip_shebang(src: &str) -> &str {
+    if src.starts_with("#!") {
+        &src[src.find('\n').unwrap_or(src.len())..]
+    } else {
+        src
     }
+}
+
+// keeps comments, pairing every token with the line breaks preceding it
+pub fn tokenize_source<S>(src: S) -> Vec<(String, usize)>
+where
+    S: Into<String>,
+{
+    let src = src.into();
+    let mut lexer = Lexer::new(&src);
     let mut tokens = Vec::new();
-    for capture in RE.captures_iter(&src.into()) {
-        if !capture[1].starts_with(';') {
-            tokens.push(capture[1].to_string());
-        }
+    while let Some(token) = lexer.next_token() {
+        tokens.push(token);
     }
     tokens
 }
 
+fn is_delimiter(c: u8) -> bool {
+    c.is_ascii_whitespace() || b"[]{}()\"`,;".contains(&c)
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(src: &'a str) -> Self {
+        Lexer {
+            src,
+            bytes: src.as_bytes(),
+            pos: 0,
+        }
+    }
+    fn peek(&self, off: usize) -> Option<u8> {
+        self.bytes.get(self.pos + off).cloned()
+    }
+    fn next_token(&mut self) -> Option<(String, usize)> {
+        let mut breaks = 0;
+        while let Some(c) = self.peek(0) {
+            if !c.is_ascii_whitespace() {
+                break;
+            }
+            if c == b'\n' {
+                breaks += 1;
+            }
+            self.pos += 1;
+        }
+        let start = self.pos;
+        match self.peek(0)? {
+            b',' if self.peek(1) == Some(b'@') => self.pos += 2,
+            b'[' | b']' | b'{' | b'}' | b'(' | b')' | b'`' | b',' | b'^' | b'@' => self.pos += 1,
+            b'\'' => self.pos += 1,
+            b'#' => match self.peek(1) {
+                Some(b'\'') | Some(b'_') => self.pos += 2,
+                Some(b'|') => self.scan_block_comment(),
+                _ => self.scan_atom(),
+            },
+            b'"' => self.scan_string(),
+            b';' => self.scan_comment(),
+            b'r' if self.raw_hashes().is_some() => self.scan_raw_string(),
+            _ => self.scan_atom(),
+        }
+        let token = self.src[start..self.pos].trim_end().to_string();
+        Some((token, breaks))
+    }
+    fn scan_atom(&mut self) {
+        self.pos += 1;
+        while let Some(c) = self.peek(0) {
+            if is_delimiter(c) {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+    fn scan_comment(&mut self) {
+        while let Some(c) = self.peek(0) {
+            if c == b'\n' {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+    // #| ... |# comments nest, and run to EOF if left open
+    fn scan_block_comment(&mut self) {
+        let mut depth = 0;
+        while let Some(c) = self.peek(0) {
+            match (c, self.peek(1)) {
+                (b'#', Some(b'|')) => {
+                    depth += 1;
+                    self.pos += 2;
+                }
+                (b'|', Some(b'#')) => {
+                    depth -= 1;
+                    self.pos += 2;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+    // strings run until the closing quote, or to EOF if there is none
+    fn scan_string(&mut self) {
+        self.pos += 1;
+        while let Some(c) = self.peek(0) {
+            self.pos += 1;
+            match c {
+                b'\\' => self.pos = (self.pos + 1).min(self.bytes.len()),
+                b'"' => return,
+                _ => (),
+            }
+        }
+    }
+    // number of '#' between the 'r' and the '"' of a raw string
+    fn raw_hashes(&self) -> Option<usize> {
+        let mut n = 0;
+        while self.peek(1 + n) == Some(b'#') {
+            n += 1;
+        }
+        match self.peek(1 + n) {
+            Some(b'"') => Some(n),
+            _ => None,
+        }
+    }
+    fn scan_raw_string(&mut self) {
+        let hashes = self.raw_hashes().unwrap_or(0);
+        self.pos += hashes + 2;
+        while let Some(c) = self.peek(0) {
+            self.pos += 1;
+            if c == b'"' && (0..hashes).all(|i| self.peek(i) == Some(b'#')) {
+                self.pos += hashes;
+                return;
+            }
+        }
+    }
+}
+
 /******************************************************************************
 ** @parser
 ******************************************************************************/
@@ -46,6 +186,14 @@ pub fn parse(tokens: &[String]) -> Result<(RVal, &[String]), RVal> {
         "]" => Err(RErrUnexpected!("']'")),
         "{" => read_rest(rest, "}"),
         "}" => Err(RErrUnexpected!("'}'")),
+        "'" => read_prefixed("quote", rest),
+        "^" if rest.first().is_some_and(|t| t == "{" || t.starts_with(':')) => read_meta(rest),
+        "#'" => read_prefixed("var", rest),
+        "#_" => {
+            let (_, rest) = parse(rest)?;
+            parse(rest)
+        }
+        _ if head.len() > 1 && head.starts_with('#') => read_prefixed(head, rest),
         _ => {
             let atom = parse_atom(head);
             match atom {
@@ -56,6 +204,110 @@ pub fn parse(tokens: &[String]) -> Result<(RVal, &[String]), RVal> {
     }
 }
 
+pub fn parse_all(tokens: &[String]) -> Result<Vec<RVal>, RVal> {
+    let mut forms = vec![];
+    let mut xs = tokens;
+    while let Some((head, rest)) = xs.split_first() {
+        let (form, new_xs) = parse(if head == "#_" { rest } else { xs })?;
+        if head != "#_" {
+            forms.push(form);
+        }
+        xs = new_xs;
+    }
+    Ok(forms)
+}
+
+pub fn read_all<S>(src: S) -> Result<Vec<RVal>, RVal>
+where
+    S: Into<String>,
+{
+    parse_all(&tokenize(src))
+}
+
+// true if src stops in the middle of a form, so that more input could
+// still complete it
+pub fn is_incomplete<S>(src: S) -> bool
+where
+    S: Into<String>,
+{
+    let tokens = tokenize_source(src);
+    if let Some((last, _)) = tokens.last() {
+        if last.starts_with("#|") && !last.ends_with("|#") {
+            return true;
+        }
+    }
+    let tokens: Vec<String> = tokens
+        .into_iter()
+        .filter(|(t, _)| !is_comment(t))
+        .map(|(t, _)| t)
+        .collect();
+    match parse_all(&tokens) {
+        Err(_RErr(e)) => e.ends_with("EOF"),
+        _ => false,
+    }
+}
+
+// 'x reads as (quote x), #'x as (var x) and #tag x as (#tag x)
+fn read_prefixed<'a>(prefix: &str, tokens: &'a [String]) -> Result<(RVal, &'a [String]), RVal> {
+    let (val, rest) = parse(tokens)?;
+    Ok((RLstArgs!([RSym(prefix), val]), rest))
+}
+
+// the last step of reading a form, done just before it is evaluated so that
+// a defreader applies to the forms after it: #tag x is replaced by what the
+// reader registered for tag returns for the unevaluated x, innermost first,
+// and the expansion is what gets evaluated or quoted
+pub fn expand_readers(x: &RVal, env: &mut REnv) -> Result<RVal, RVal> {
+    Ok(expand(x, env)?.unwrap_or_else(|| x.clone()))
+}
+
+// None if x has no #tag forms
+fn expand(x: &RVal, env: &mut REnv) -> Result<Option<RVal>, RVal> {
+    let vs = match x {
+        RLst(vs) | RVec(vs) => vs,
+        RMap(m) => {
+            let mut kvs = Vec::with_capacity(m.len());
+            let mut changed = false;
+            for (k, v) in m.iter() {
+                let (ek, ev) = (expand(k, env)?, expand(v, env)?);
+                changed |= ek.is_some() || ev.is_some();
+                kvs.push((ek.unwrap_or_else(|| k.clone()), ev.unwrap_or_else(|| v.clone())));
+            }
+            return Ok(changed.then(|| RMap(Rc::new(kvs.into_iter().collect()))));
+        }
+        _ => return Ok(None),
+    };
+    let mut expanded: Option<Vec<RVal>> = None;
+    for (i, v) in vs.iter().enumerate() {
+        if let Some(e) = expand(v, env)? {
+            expanded.get_or_insert_with(|| vs.to_vec())[i] = e;
+        }
+    }
+    if let (RLst(_), [_RSym(s), form]) = (x, expanded.as_deref().unwrap_or(vs)) {
+        if s.len() > 1 && s.starts_with('#') {
+            return match env.read_tagged(&s[1..], form) {
+                e @ _RErr(_) => Err(e),
+                v => Ok(Some(v)),
+            };
+        }
+    }
+    Ok(expanded.map(|vs| match x {
+        RLst(_) => RLst(Rc::new(vs)),
+        _ => RVec(Rc::new(vs)),
+    }))
+}
+
+// ^{:k v} x reads as (with-meta x {:k v}) and ^:k x as (with-meta x {:k true})
+fn read_meta(tokens: &[String]) -> Result<(RVal, &[String]), RVal> {
+    let (m, rest) = parse(tokens)?;
+    let m = match &m {
+        _RSym(_) => RMapArgs!([m.clone(), RBool(true)]),
+        _ => m,
+    };
+    let (val, rest) = parse(rest)?;
+    Ok((RLstArgs!([RSym("with-meta"), val, m]), rest))
+}
+
 fn read_rest<'a>(tokens: &'a [String], end: &str) -> Result<(RVal, &'a [String]), RVal> {
     let mut vs = vec![];
     let mut xs = tokens;
@@ -74,51 +326,29 @@ fn read_rest<'a>(tokens: &'a [String], end: &str) -> Result<(RVal, &'a [String])
                 return Err(RErr("internal error (read rest)"));
             }
         }
-        let (new_vs, new_xs) = parse(&xs)?;
+        if next == "#_" {
+            let (_, new_xs) = parse(rest)?;
+            xs = new_xs;
+            continue;
+        }
+        let (new_vs, new_xs) = parse(xs)?;
         vs.push(new_vs);
         xs = new_xs;
     }
 }
 
 fn parse_atom(atom: &str) -> RVal {
-    lazy_static! {
-        static ref INT_RE: Regex = Regex::new(
-            r#"(?mx)
-            (^[-+]?([1-9]\d*|0)$)"#
-        )
-        .unwrap();
-        static ref FLT_RE: Regex = Regex::new(
-            r#"(?mx)
-            (^[-+]?(\.[0-9]+|(([1-9]\d*|0)\.[0-9]*))$)"#
-        )
-        .unwrap();
-        static ref STR_RE: Regex = Regex::new(
-            r#"(?mx)
-            "(?:\\.|[^\\"])*""#
-        )
-        .unwrap();
-    }
     match atom {
         "nil" => RNil,
         "false" => RBool(false),
         "true" => RBool(true),
         _ => {
-            if INT_RE.is_match(&atom) {
-                let num = atom.parse();
-                match num {
-                    Ok(i) => RInt(i),
-                    Err(_) => RErr("integer overflow"),
-                }
-            } else if FLT_RE.is_match(&atom) {
-                let num = atom.parse();
-                match num {
-                    Ok(f) => RFlt(f),
-                    Err(_) => RErr("floating point overflow"),
-                }
-            } else if STR_RE.is_match(&atom) {
-                RStr(unescape(&atom[1..atom.len() - 1]))
-            } else if atom.starts_with('"') {
-                RErrExpected!("'\"'", "EOF")
+            if atom.starts_with('"') {
+                parse_string(atom)
+            } else if let Some(raw) = parse_raw_string(atom) {
+                raw
+            } else if let Some(num) = parse_number(atom) {
+                num
             } else {
                 RSym(atom)
             }
@@ -126,12 +356,126 @@ fn parse_atom(atom: &str) -> RVal {
     }
 }
 
-fn unescape<S>(src: S) -> String
-where
-    S: Into<String>,
-{
-    src.into()
-        .replace("\\\"", "\"")
-        .replace("\\n", "\n")
-        .replace("\\\\", "\\")
+fn parse_string(atom: &str) -> RVal {
+    let body = &atom[1..];
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                return if chars.next().is_none() {
+                    RStr(out)
+                } else {
+                    RErrUnexpected!("'\"'")
+                };
+            }
+            '\\' => match chars.next() {
+                Some('"') => out.push('"'),
+                Some('\\') => out.push('\\'),
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('r') => out.push('\r'),
+                Some('0') => out.push('\0'),
+                Some('u') => match unicode_escape(&mut chars) {
+                    Some(c) => out.push(c),
+                    None => return RErr("invalid unicode escape"),
+                },
+                Some(c) => return RErr(format!("invalid escape '\\{}'", c)),
+                None => break,
+            },
+            _ => out.push(c),
+        }
+    }
+    RErrExpected!("'\"'", "EOF")
+}
+
+// \u{XXXX} with one to six hex digits naming a valid code point
+fn unicode_escape(chars: &mut std::str::Chars) -> Option<char> {
+    if chars.next()? != '{' {
+        return None;
+    }
+    let mut hex = String::new();
+    loop {
+        match chars.next()? {
+            '}' => break,
+            c if c.is_ascii_hexdigit() && hex.len() < 6 => hex.push(c),
+            _ => return None,
+        }
+    }
+    std::char::from_u32(u32::from_str_radix(&hex, 16).ok()?)
+}
+
+fn parse_raw_string(atom: &str) -> Option<RVal> {
+    let rest = atom.strip_prefix('r')?;
+    let hashes = rest.len() - rest.trim_start_matches('#').len();
+    let body = rest[hashes..].strip_prefix('"')?;
+    let closing = format!("\"{}", "#".repeat(hashes));
+    match body.strip_suffix(&closing[..]) {
+        Some(s) => Some(RStr(s)),
+        None => Some(RErrExpected!(format!("'{}'", closing), "EOF")),
+    }
+}
+
+// digits in the given radix, separated by single underscores
+fn digits(s: &str, radix: u32) -> bool {
+    !s.is_empty()
+        && !s.starts_with('_')
+        && !s.ends_with('_')
+        && !s.contains("__")
+        && s.chars().all(|c| c == '_' || c.is_digit(radix))
+}
+
+pub fn parse_number(atom: &str) -> Option<RVal> {
+    let (neg, unsigned) = match atom.as_bytes().first()? {
+        b'-' => (true, &atom[1..]),
+        b'+' => (false, &atom[1..]),
+        _ => (false, atom),
+    };
+    let radix = match unsigned.get(..2) {
+        Some("0x") | Some("0X") => 16,
+        Some("0o") | Some("0O") => 8,
+        Some("0b") | Some("0B") => 2,
+        _ => 10,
+    };
+    if radix != 10 {
+        let ds = &unsigned[2..];
+        if !digits(ds, radix) {
+            return None;
+        }
+        let sign = if neg { "-" } else { "" };
+        let num = i64::from_str_radix(&format!("{}{}", sign, ds.replace('_', "")), radix);
+        return Some(match num {
+            Ok(i) => RInt(i),
+            Err(_) => RErr("integer overflow"),
+        });
+    }
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(i) => (&unsigned[..i], Some(&unsigned[i + 1..])),
+        None => (unsigned, None),
+    };
+    let (int, frac) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], Some(&mantissa[i + 1..])),
+        None => (mantissa, None),
+    };
+    let int_ok = (int.is_empty() && frac.is_some_and(|f| !f.is_empty())) || digits(int, 10);
+    let frac_ok = frac.is_none_or(|f| f.is_empty() || digits(f, 10));
+    let exp_ok = exponent.is_none_or(|e| digits(e.strip_prefix(['-', '+']).unwrap_or(e), 10));
+    if !int_ok || !frac_ok || !exp_ok {
+        return None;
+    }
+    if int.len() > 1 && int.starts_with('0') && frac.is_none() && exponent.is_none() {
+        return None;
+    }
+    let clean = atom.replace('_', "");
+    if frac.is_none() && exponent.is_none() {
+        Some(match clean.parse() {
+            Ok(i) => RInt(i),
+            Err(_) => RErr("integer overflow"),
+        })
+    } else {
+        Some(match clean.parse::<f64>() {
+            Ok(f) if f.is_finite() => RFlt(f),
+            _ => RErr("floating point overflow"),
+        })
+    }
 }
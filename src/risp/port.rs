@@ -0,0 +1,452 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, Write};
+use std::rc::Rc;
+
+use crate::risp::{eval, REnv, RErr, RStr, RSym, RVal, RVal::*};
+
+/******************************************************************************
+** @ports
+******************************************************************************/
+
+// where a port reads from or writes to
+enum Stream {
+    Stdin,
+    Stdout,
+    Stderr,
+    // files and strings being read
+    Reader(Box<dyn BufRead>),
+    File(BufWriter<File>),
+    // what has been written to a string port so far
+    Str(String),
+    Closed,
+}
+
+pub struct Port {
+    pub name: String,
+    pub input: bool,
+    stream: RefCell<Stream>,
+}
+
+impl Port {
+    fn open(name: &str, input: bool, stream: Stream) -> RVal {
+        RPort(Rc::new(Port {
+            name: name.to_string(),
+            input,
+            stream: RefCell::new(stream),
+        }))
+    }
+    pub fn stdin() -> RVal {
+        Port::open("stdin", true, Stream::Stdin)
+    }
+    pub fn stdout() -> RVal {
+        Port::open("stdout", false, Stream::Stdout)
+    }
+    pub fn stderr() -> RVal {
+        Port::open("stderr", false, Stream::Stderr)
+    }
+    pub fn is_closed(&self) -> bool {
+        matches!(*self.stream.borrow(), Stream::Closed)
+    }
+    fn error(&self, op: &str, msg: impl ToString) -> RVal {
+        RErr(format!("{}: {}: {}", op, self.name, msg.to_string()))
+    }
+    // runs f with the reader of an input port
+    fn read<T>(
+        &self,
+        op: &str,
+        f: impl FnOnce(&mut dyn BufRead) -> io::Result<T>,
+    ) -> Result<T, RVal> {
+        let mut stream = self.stream.borrow_mut();
+        let res = match &mut *stream {
+            Stream::Stdin => f(&mut io::stdin().lock()),
+            Stream::Reader(r) => f(r),
+            Stream::Closed => return Err(self.error(op, "port is closed")),
+            _ => return Err(self.error(op, "not an input port")),
+        };
+        res.map_err(|e| self.error(op, e.kind()))
+    }
+    // the standard output ports write to the output of env
+    pub fn write(&self, op: &str, s: &str, env: &REnv) -> Result<(), RVal> {
+        let mut stream = self.stream.borrow_mut();
+        let res = match &mut *stream {
+            Stream::Stdout => env.output.borrow_mut().stdout.write_all(s.as_bytes()),
+            Stream::Stderr => env.output.borrow_mut().stderr.write_all(s.as_bytes()),
+            Stream::File(w) => w.write_all(s.as_bytes()),
+            Stream::Str(out) => {
+                out.push_str(s);
+                Ok(())
+            }
+            Stream::Closed => return Err(self.error(op, "port is closed")),
+            _ => return Err(self.error(op, "not an output port")),
+        };
+        res.map_err(|e| self.error(op, e.kind()))
+    }
+    pub fn flush(&self, env: &REnv) -> Result<(), RVal> {
+        let mut stream = self.stream.borrow_mut();
+        let res = match &mut *stream {
+            Stream::Stdout => env.output.borrow_mut().stdout.flush(),
+            Stream::Stderr => env.output.borrow_mut().stderr.flush(),
+            Stream::File(w) => w.flush(),
+            _ => Ok(()),
+        };
+        res.map_err(|e| self.error("flush", e.kind()))
+    }
+    // the standard streams stay open, they are only flushed
+    pub fn close(&self, env: &REnv) -> Result<(), RVal> {
+        self.flush(env)?;
+        let mut stream = self.stream.borrow_mut();
+        match &*stream {
+            Stream::Stdin | Stream::Stdout | Stream::Stderr => (),
+            _ => *stream = Stream::Closed,
+        }
+        Ok(())
+    }
+}
+
+// a line without its line ending, or None at the end of the input
+fn read_line(r: &mut dyn BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if r.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Some(line))
+}
+
+// a line from stdin, for reading without a port
+pub fn read_stdin_line() -> io::Result<Option<String>> {
+    read_line(&mut io::stdin().lock())
+}
+
+// one UTF-8 character, or None at the end of the input; bytes that are not
+// UTF-8 are an error, and reading goes on after them
+fn read_char(r: &mut dyn BufRead) -> io::Result<Option<char>> {
+    let width = match r.fill_buf()?.first() {
+        None => return Ok(None),
+        Some(0xc0..=0xdf) => 2,
+        Some(0xe0..=0xef) => 3,
+        Some(0xf0..=0xf7) => 4,
+        Some(_) => 1,
+    };
+    // stop at a byte that does not continue the character, to leave it for
+    // the next read
+    let mut buf = [0; 4];
+    let mut len = 0;
+    while len < width {
+        match r.fill_buf()?.first() {
+            Some(&b) if len == 0 || b & 0xc0 == 0x80 => buf[len] = b,
+            _ => break,
+        }
+        r.consume(1);
+        len += 1;
+    }
+    match std::str::from_utf8(&buf[..len]) {
+        Ok(s) => Ok(s.chars().next()),
+        Err(_) => Err(io::ErrorKind::InvalidData.into()),
+    }
+}
+
+/******************************************************************************
+** @ports into environment
+******************************************************************************/
+
+pub fn load_port(env: &mut REnv) {
+    env.def("*stdin*", Port::stdin());
+    env.def("*stdout*", Port::stdout());
+    env.def("*stderr*", Port::stderr());
+    env.defn(
+        "open-input-file",
+        open_input_file,
+        "Str",
+        "a port reading from a file",
+    );
+    env.defn(
+        "open-output-file",
+        open_output_file,
+        "Str",
+        "a port writing to a file, which is created or emptied",
+    );
+    env.defn(
+        "open-input-string",
+        open_input_string,
+        "Str",
+        "a port reading from a string",
+    );
+    env.defn(
+        "open-output-string",
+        open_output_string,
+        "",
+        "a port collecting what is written to it, see get-output-string",
+    );
+    env.defn(
+        "get-output-string",
+        get_output_string,
+        "Port",
+        "what has been written to a port made with open-output-string",
+    );
+    env.defn(
+        "read-line",
+        read_line_from,
+        "Port?",
+        "the next line from a port or *stdin* without its line ending, or nil\n\
+         at the end",
+    );
+    env.defn(
+        "read-char",
+        read_char_from,
+        "Port?",
+        "the next character from a port or *stdin* as a string, or nil at the\n\
+         end",
+    );
+    env.defn(
+        "read-lines",
+        read_lines_from,
+        "Port?",
+        "the remaining lines of a port or *stdin* as a Vec",
+    );
+    env.defn(
+        "read-all",
+        read_all_from,
+        "Port?",
+        "the rest of a port or *stdin* as a string",
+    );
+    env.defn(
+        "eof?",
+        eof,
+        "Port?",
+        "true if there is nothing left to read from a port or *stdin*",
+    );
+    env.defn(
+        "write-to",
+        write_to,
+        "Port Any ...",
+        "write values to a port, strings without quotes",
+    );
+    env.defn("flush", flush, "Port", "write out what a port has buffered");
+    env.defn(
+        "close",
+        close,
+        "Port",
+        "flush and close a port, the standard ports stay open",
+    );
+    env.defn(
+        "with-open",
+        with_open,
+        "(Sym Port) Any ...",
+        "evaluate expressions with a symbol bound to a port, closing it after",
+    );
+}
+
+// the path or string an argument evaluates to
+fn str_arg(args: &[RVal], env: &mut REnv) -> Result<String, RVal> {
+    match args {
+        [x] => match eval(x, env) {
+            _RStr(s) => Ok(s.to_string()),
+            e @ _RErr(_) => Err(e),
+            v => Err(RErrExpected!("Str", v.variant())),
+        },
+        _ => Err(RErrExpected!("(Str)", RLstArgs![args].variant())),
+    }
+}
+
+fn port_arg(x: &RVal, env: &mut REnv) -> Result<Rc<Port>, RVal> {
+    match eval(x, env) {
+        RPort(p) => Ok(p),
+        e @ _RErr(_) => Err(e),
+        v => Err(RErrExpected!("Port", v.variant())),
+    }
+}
+
+// a builtin taking only a port
+fn with_port(args: &[RVal], env: &mut REnv, f: fn(&Port) -> Result<RVal, RVal>) -> RVal {
+    let port = match args {
+        [x] => port_arg(x, env),
+        _ => return RErrExpected!("(Port)", RLstArgs![args].variant()),
+    };
+    match port.and_then(|p| f(&p)) {
+        Ok(v) | Err(v) => v,
+    }
+}
+
+// a builtin reading from a port, *stdin* if none is given
+fn with_input(args: &[RVal], env: &mut REnv, f: fn(&Port) -> Result<RVal, RVal>) -> RVal {
+    let port = match args {
+        [] => port_arg(&RSym("*stdin*"), env),
+        [x] => port_arg(x, env),
+        _ => return RErrExpected!("(Port?)", RLstArgs![args].variant()),
+    };
+    match port.and_then(|p| f(&p)) {
+        Ok(v) | Err(v) => v,
+    }
+}
+
+fn open_input_file(args: &[RVal], env: &mut REnv) -> RVal {
+    let path = match str_arg(args, env) {
+        Ok(path) => path,
+        Err(e) => return e,
+    };
+    match File::open(&path) {
+        Ok(f) => Port::open(&path, true, Stream::Reader(Box::new(BufReader::new(f)))),
+        Err(e) => RErr(format!("open-input-file: {}: {}", path, e.kind())),
+    }
+}
+
+fn open_output_file(args: &[RVal], env: &mut REnv) -> RVal {
+    let path = match str_arg(args, env) {
+        Ok(path) => path,
+        Err(e) => return e,
+    };
+    match File::create(&path) {
+        Ok(f) => Port::open(&path, false, Stream::File(BufWriter::new(f))),
+        Err(e) => RErr(format!("open-output-file: {}: {}", path, e.kind())),
+    }
+}
+
+fn open_input_string(args: &[RVal], env: &mut REnv) -> RVal {
+    match str_arg(args, env) {
+        Ok(s) => {
+            let reader = Cursor::new(s.into_bytes());
+            Port::open("string", true, Stream::Reader(Box::new(reader)))
+        }
+        Err(e) => e,
+    }
+}
+
+fn open_output_string(args: &[RVal], _env: &mut REnv) -> RVal {
+    match args {
+        [] => Port::open("string", false, Stream::Str(String::new())),
+        _ => RErrExpected!("()", RLstArgs![args].variant()),
+    }
+}
+
+fn get_output_string(args: &[RVal], env: &mut REnv) -> RVal {
+    with_port(args, env, |p| match &*p.stream.borrow() {
+        Stream::Str(s) => Ok(RStr(&s[..])),
+        _ => Err(p.error("get-output-string", "not an output string port")),
+    })
+}
+
+fn read_line_from(args: &[RVal], env: &mut REnv) -> RVal {
+    with_input(args, env, |p| {
+        let line = p.read("read-line", read_line)?;
+        Ok(line.map_or(RNil, RStr))
+    })
+}
+
+fn read_char_from(args: &[RVal], env: &mut REnv) -> RVal {
+    with_input(args, env, |p| {
+        let c = p.read("read-char", read_char)?;
+        Ok(c.map_or(RNil, |c| RStr(c.to_string())))
+    })
+}
+
+fn read_lines_from(args: &[RVal], env: &mut REnv) -> RVal {
+    with_input(args, env, |p| {
+        let lines = p.read("read-lines", |r| {
+            let mut lines = vec![];
+            while let Some(line) = read_line(r)? {
+                lines.push(RStr(line));
+            }
+            Ok(lines)
+        })?;
+        Ok(RVecArgs!(lines))
+    })
+}
+
+fn read_all_from(args: &[RVal], env: &mut REnv) -> RVal {
+    with_input(args, env, |p| {
+        let s = p.read("read-all", |r| {
+            let mut s = String::new();
+            r.read_to_string(&mut s).map(|_| s)
+        })?;
+        Ok(RStr(s))
+    })
+}
+
+fn eof(args: &[RVal], env: &mut REnv) -> RVal {
+    with_input(args, env, |p| {
+        let at_end = p.read("eof?", |r| r.fill_buf().map(|b| b.is_empty()))?;
+        Ok(RBool(at_end))
+    })
+}
+
+fn write_to(args: &[RVal], env: &mut REnv) -> RVal {
+    let port = match args.first() {
+        Some(x) => match port_arg(x, env) {
+            Ok(p) => p,
+            Err(e) => return e,
+        },
+        None => return RErrExpected!("(Port Any ...)", RLstArgs![args].variant()),
+    };
+    for x in args[1..].iter() {
+        let res = match eval(x, env) {
+            e @ _RErr(_) => return e,
+            _RStr(s) => port.write("write-to", &s, env),
+            v => port.write("write-to", &v.to_string(), env),
+        };
+        if let Err(e) = res {
+            return e;
+        }
+    }
+    RLstArgs![[]]
+}
+
+fn flush(args: &[RVal], env: &mut REnv) -> RVal {
+    let res = match args {
+        [x] => port_arg(x, env).and_then(|p| p.flush(env)),
+        _ => return RErrExpected!("(Port)", RLstArgs![args].variant()),
+    };
+    match res {
+        Ok(()) => RLstArgs![[]],
+        Err(e) => e,
+    }
+}
+
+fn close(args: &[RVal], env: &mut REnv) -> RVal {
+    let res = match args {
+        [x] => port_arg(x, env).and_then(|p| p.close(env)),
+        _ => return RErrExpected!("(Port)", RLstArgs![args].variant()),
+    };
+    match res {
+        Ok(()) => RLstArgs![[]],
+        Err(e) => e,
+    }
+}
+
+fn with_open(args: &[RVal], env: &mut REnv) -> RVal {
+    let (name, expr, body) = match args {
+        [RLst(binding), body @ ..] if !body.is_empty() => match &binding[..] {
+            [_RSym(name), expr] => (name, expr, body),
+            _ => return RErrExpected!("(Sym Port)", args[0].variant()),
+        },
+        _ => return RErrExpected!("((Sym Port) Any ...)", RLstArgs![args].variant()),
+    };
+    let port = match port_arg(expr, env) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let mut scope = env.clone();
+    scope.def(&name[..], RPort(port.clone()));
+    let mut res = RNil;
+    for x in body {
+        res = eval(x, &mut scope);
+        if let _RErr(_) = res {
+            break;
+        }
+    }
+    // the port is closed even if the body failed, whose error comes first
+    match (port.close(env), res) {
+        (_, e @ _RErr(_)) | (Err(e), _) => e,
+        (Ok(()), res) => res,
+    }
+}
@@ -0,0 +1,193 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use crate::risp::{eval, reader_prefix, REnv, RErr, RVal, RVal::*};
+
+/******************************************************************************
+** @pretty printer
+******************************************************************************/
+
+pub const PRINT_WIDTH: usize = 80;
+
+// forms whose leading arguments stay on the first line, the rest of the
+// arguments being indented as a body
+pub fn body_args(form: &str) -> Option<usize> {
+    match form {
+        "do" | "cond" | "with-output-to-string" | "with-error-to-string" => Some(0),
+        "fn" | "let" | "while" | "with-open" => Some(1),
+        "for" => Some(3),
+        _ => None,
+    }
+}
+
+pub fn pprint(val: &RVal, width: usize) -> String {
+    let mut out = String::new();
+    pp(val, 0, width, &mut out);
+    out
+}
+
+fn fits(s: &str, col: usize, width: usize) -> bool {
+    !s.contains('\n') && col + s.chars().count() <= width
+}
+
+fn newline(col: usize, out: &mut String) {
+    out.push('\n');
+    out.push_str(&" ".repeat(col));
+}
+
+fn pp(val: &RVal, col: usize, width: usize, out: &mut String) {
+    let flat = format!("{:#}", val);
+    if fits(&flat, col, width) {
+        out.push_str(&flat);
+        return;
+    }
+    match val {
+        RLst(vs) if !vs.is_empty() => match reader_prefix(vs) {
+            Some(prefix) => {
+                out.push_str(&prefix);
+                pp(&vs[1], col + prefix.chars().count(), width, out);
+            }
+            None => pp_list(vs, col, width, out),
+        },
+        RVec(vs) if !vs.is_empty() => pp_seq("[", "]", vs, col, width, out),
+        RMap(hm) if !hm.is_empty() => {
+            out.push('{');
+            for (i, (k, v)) in hm.iter().enumerate() {
+                if i > 0 {
+                    newline(col + 1, out);
+                }
+                pp(k, col + 1, width, out);
+                let key_end = col + 1 + format!("{:#}", k).chars().count() + 1;
+                if fits(&format!("{:#}", v), key_end, width) {
+                    out.push(' ');
+                    pp(v, key_end, width, out);
+                } else {
+                    newline(col + 2, out);
+                    pp(v, col + 2, width, out);
+                }
+            }
+            out.push('}');
+        }
+        RLfn(l) => {
+            out.push_str("(Fn ");
+            pp(&l.params, col + 4, width, out);
+            newline(col + 2, out);
+            pp(&l.body, col + 2, width, out);
+            out.push(')');
+        }
+        _ => out.push_str(&flat),
+    }
+}
+
+fn pp_seq(open: &str, close: &str, vs: &[RVal], col: usize, width: usize, out: &mut String) {
+    out.push_str(open);
+    for (i, v) in vs.iter().enumerate() {
+        if i > 0 {
+            newline(col + 1, out);
+        }
+        pp(v, col + 1, width, out);
+    }
+    out.push_str(close);
+}
+
+fn pp_list(vs: &[RVal], col: usize, width: usize, out: &mut String) {
+    let head = match &vs[0] {
+        _RSym(s) => s.to_string(),
+        _ => return pp_seq("(", ")", vs, col, width, out),
+    };
+    out.push('(');
+    out.push_str(&head);
+    let args = &vs[1..];
+    if args.is_empty() {
+        out.push(')');
+        return;
+    }
+    if let Some(n) = body_args(&head) {
+        // (fn "docstring" (parameters)
+        //   body)
+        let n = match args.first() {
+            Some(_RStr(_)) if head == "fn" => n + 1,
+            _ => n,
+        };
+        // (let name
+        //   body)
+        let mut at = col + 1 + head.chars().count();
+        for v in args.iter().take(n) {
+            out.push(' ');
+            pp(v, at + 1, width, out);
+            at = last_line_len(out);
+        }
+        for v in args.iter().skip(n) {
+            newline(col + 2, out);
+            pp(v, col + 2, width, out);
+        }
+    } else {
+        // (cond (a b)
+        //       (c d))
+        let align = col + 1 + head.chars().count() + 1;
+        let align = if align > width / 2 { col + 2 } else { align };
+        if align == col + 2 {
+            for v in args.iter() {
+                newline(align, out);
+                pp(v, align, width, out);
+            }
+        } else {
+            out.push(' ');
+            for (i, v) in args.iter().enumerate() {
+                if i > 0 {
+                    newline(align, out);
+                }
+                pp(v, align, width, out);
+            }
+        }
+    }
+    out.push(')');
+}
+
+fn last_line_len(out: &str) -> usize {
+    match out.rfind('\n') {
+        Some(i) => out[i + 1..].chars().count(),
+        None => out.chars().count(),
+    }
+}
+
+/******************************************************************************
+** @pretty printer into environment
+******************************************************************************/
+
+pub fn load_pprint(env: &mut REnv) {
+    env.def("*print-width*", RInt(PRINT_WIDTH as i64));
+    env.defn(
+        "pprint",
+        builtin_pprint,
+        "Any Int?",
+        "print a value laid out to fit *print-width* or the given width",
+    );
+}
+
+pub fn print_width(env: &REnv) -> usize {
+    match env.get("*print-width*") {
+        Some(RInt(w)) if w > 0 => w as usize,
+        _ => PRINT_WIDTH,
+    }
+}
+
+fn builtin_pprint(args: &[RVal], env: &mut REnv) -> RVal {
+    let width = match args.len() {
+        1 => print_width(env),
+        2 => match eval(&args[1], env) {
+            RInt(w) if w > 0 => w as usize,
+            _ => return RErrExpected!("(Any Int)", RLstArgs![args].variant()),
+        },
+        _ => return RErrExpected!("(Any) | (Any Int)", RLstArgs![args].variant()),
+    };
+    let val = eval(&args[0], env);
+    if let _RErr(_) = val {
+        return val;
+    }
+    match env.write_out(&format!("{}\n", pprint(&val, width))) {
+        Ok(()) => RLstArgs![[]],
+        Err(e) => e,
+    }
+}
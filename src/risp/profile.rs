@@ -0,0 +1,175 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use fnv::FnvHashMap;
+
+use std::fs;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::risp::{eval, REnv, RErr, RVal, RVal::*};
+
+/******************************************************************************
+** @profiler
+******************************************************************************/
+
+struct Call {
+    name: String,
+    start: Instant,
+    // time spent in the calls made by this one
+    children: Duration,
+}
+
+#[derive(Default)]
+struct Stat {
+    calls: u64,
+    inclusive: Duration,
+    exclusive: Duration,
+}
+
+pub struct Profiler {
+    active: bool,
+    stack: Vec<Call>,
+    stats: FnvHashMap<String, Stat>,
+    // exclusive time spent in each call stack, as name;name;name
+    folded: FnvHashMap<String, Duration>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler {
+            active: false,
+            stack: vec![],
+            stats: FnvHashMap::default(),
+            folded: FnvHashMap::default(),
+        }
+    }
+    pub fn start(&mut self) {
+        *self = Profiler::new();
+        self.active = true;
+    }
+    // writes the report to out, usually stderr, and the folded stacks to path
+    pub fn stop(&mut self, path: Option<&str>, out: &mut dyn Write) {
+        self.active = false;
+        let _ = write!(out, "{}", self.report());
+        if let Some(path) = path {
+            if let Err(e) = fs::write(path, self.folded()) {
+                let _ = writeln!(out, "could not write {}: {}", path, e);
+            }
+        }
+    }
+    pub fn active(&self) -> bool {
+        self.active
+    }
+    fn enter(&mut self, name: &RVal) {
+        let name = match name {
+            _RSym(s) => s.to_string(),
+            _ => "(fn)".to_string(),
+        };
+        self.stack.push(Call {
+            name,
+            start: Instant::now(),
+            children: Duration::default(),
+        });
+    }
+    fn exit(&mut self) {
+        let call = match self.stack.pop() {
+            Some(call) => call,
+            None => return,
+        };
+        let elapsed = call.start.elapsed();
+        let exclusive = elapsed.saturating_sub(call.children);
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += elapsed;
+        }
+        let mut path: Vec<&str> = self.stack.iter().map(|c| &c.name[..]).collect();
+        // recursive calls are already counted by the outermost one
+        let outermost = !path.contains(&&call.name[..]);
+        path.push(&call.name[..]);
+        *self.folded.entry(path.join(";")).or_default() += exclusive;
+        let stat = self.stats.entry(call.name).or_default();
+        stat.calls += 1;
+        stat.exclusive += exclusive;
+        if outermost {
+            stat.inclusive += elapsed;
+        }
+    }
+    // one line per function, the most expensive first
+    pub fn report(&self) -> String {
+        let mut stats: Vec<(&String, &Stat)> = self.stats.iter().collect();
+        stats.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(b.0)));
+        let mut out = format!(
+            "{:>10} {:>12} {:>12}  {}\n",
+            "calls", "inclusive", "exclusive", "function"
+        );
+        for (name, s) in stats {
+            out.push_str(&format!(
+                "{:>10} {:>12} {:>12}  {}\n",
+                s.calls,
+                format!("{:.3}ms", s.inclusive.as_secs_f64() * 1000.0),
+                format!("{:.3}ms", s.exclusive.as_secs_f64() * 1000.0),
+                name
+            ));
+        }
+        out
+    }
+    // the input format of flamegraph tools, in microseconds
+    pub fn folded(&self) -> String {
+        let mut stacks: Vec<(&String, &Duration)> = self.folded.iter().collect();
+        stacks.sort();
+        stacks
+            .iter()
+            .map(|(k, d)| format!("{} {}\n", k, d.as_micros()))
+            .collect()
+    }
+}
+
+// start timing a call if profiling, returning whether it was
+pub fn profile_enter(name: &RVal, env: &REnv) -> bool {
+    let mut profiler = env.profiler.borrow_mut();
+    if profiler.active {
+        profiler.enter(name);
+    }
+    profiler.active
+}
+
+pub fn profile_exit(env: &REnv) {
+    env.profiler.borrow_mut().exit();
+}
+
+/******************************************************************************
+** @profiler into environment
+******************************************************************************/
+
+pub fn load_profile(env: &mut REnv) {
+    env.defn(
+        "profile",
+        profile,
+        "Any Str?",
+        "evaluate an expression, report the time spent in each function\n\
+         to stderr and optionally write the folded stacks to a file",
+    );
+}
+
+fn profile(args: &[RVal], env: &mut REnv) -> RVal {
+    let path = match args {
+        [_] => None,
+        [_, p] => match eval(p, env) {
+            _RStr(s) => Some(s.to_string()),
+            v => return RErrExpected!("(Any Str?)", v.variant()),
+        },
+        _ => return RErrExpected!("(Any Str?)", RLstArgs![args].variant()),
+    };
+    // profiling is already on, as with --profile
+    if env.profiler.borrow().active {
+        return eval(&args[0], env);
+    }
+    env.profiler.borrow_mut().start();
+    env.update_hooks();
+    let res = eval(&args[0], env);
+    let stderr = &mut env.output.borrow_mut().stderr;
+    env.profiler.borrow_mut().stop(path.as_deref(), stderr.as_mut());
+    env.update_hooks();
+    res
+}
@@ -4,12 +4,16 @@
 
 use fnv::FnvHashMap;
 
-use std::fs;
-use std::sync::Arc;
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+use std::rc::Rc;
 
 use crate::risp::{
-    eval, eval_lambda, load_arithmetic, load_constructs, load_io, load_logic, rep, RErr, RLambda,
-    RVal, RVal::*,
+    attach_meta, eval, eval_builtin, eval_file, eval_lambda, eval_read, load_arithmetic,
+    load_constructs, load_debug, load_doc, load_files, load_io, load_logic, load_meta, load_module,
+    load_output, load_port, load_pprint, load_profile, load_testing, load_trace, merge_meta,
+    quote_meta, read_all, read_module, rep, special_form_docs, split_meta, Debugger, Modules,
+    Output, Profiler, RDoc, RErr, RLambda, RSym, RVal, RVal::*, Tests, Tracer, ValueMeta,
 };
 
 /******************************************************************************
@@ -19,19 +23,104 @@ use crate::risp::{
 #[derive(Clone)]
 pub struct REnv {
     pub symbols: FnvHashMap<String, RVal>,
+    // the bindings of the module whose function is being called, looked up
+    // after symbols
+    pub scope: Option<Rc<FnvHashMap<String, RVal>>>,
+    pub readers: FnvHashMap<String, RVal>,
+    pub meta: FnvHashMap<String, RVal>,
+    pub docs: Rc<FnvHashMap<String, RDoc>>,
+    // shared by every environment cloned from this one
+    pub debugger: Rc<RefCell<Debugger>>,
+    pub tracer: Rc<RefCell<Tracer>>,
+    pub profiler: Rc<RefCell<Profiler>>,
+    pub tests: Rc<RefCell<Tests>>,
+    pub modules: Rc<RefCell<Modules>>,
+    pub output: Rc<RefCell<Output>>,
+    pub value_meta: Rc<RefCell<ValueMeta>>,
+    // whether the debugger, tracer or profiler is on, so that calls only
+    // pay for them when one is
+    pub hooked: Rc<Cell<bool>>,
+    // the status given to (exit), which returns an error so that evaluation
+    // stops and main can finish up before exiting with it
+    pub exit: Rc<Cell<Option<i32>>>,
 }
 
 impl REnv {
     pub fn new() -> Self {
         let mut env = REnv {
             symbols: FnvHashMap::default(),
+            scope: None,
+            readers: FnvHashMap::default(),
+            meta: FnvHashMap::default(),
+            docs: Rc::new(special_form_docs().into_iter().collect()),
+            debugger: Rc::new(RefCell::new(Debugger::new())),
+            tracer: Rc::new(RefCell::new(Tracer::new())),
+            profiler: Rc::new(RefCell::new(Profiler::new())),
+            tests: Rc::new(RefCell::new(Tests::new())),
+            modules: Rc::new(RefCell::new(Modules::new())),
+            output: Rc::new(RefCell::new(Output::new())),
+            value_meta: Rc::new(RefCell::new(ValueMeta::new())),
+            hooked: Rc::new(Cell::new(false)),
+            exit: Rc::new(Cell::new(None)),
         };
         load_arithmetic(&mut env);
         load_logic(&mut env);
         load_io(&mut env);
+        load_files(&mut env);
+        load_port(&mut env);
+        load_output(&mut env);
         load_constructs(&mut env);
+        load_pprint(&mut env);
+        load_meta(&mut env);
+        load_doc(&mut env);
+        load_debug(&mut env);
+        load_trace(&mut env);
+        load_profile(&mut env);
+        load_testing(&mut env);
+        load_module(&mut env);
         env
     }
+    // an environment with only the builtins, sharing the debugger, tracer,
+    // profiler, tests, modules, output and exit status of this one
+    pub fn fresh(&self) -> Self {
+        REnv {
+            debugger: self.debugger.clone(),
+            tracer: self.tracer.clone(),
+            profiler: self.profiler.clone(),
+            tests: self.tests.clone(),
+            modules: self.modules.clone(),
+            output: self.output.clone(),
+            value_meta: self.value_meta.clone(),
+            hooked: self.hooked.clone(),
+            exit: self.exit.clone(),
+            ..REnv::new()
+        }
+    }
+    // a copy whose debugger, tracer, profiler, tests, modules and value
+    // metadata are its own, so that nothing done in it reaches this one;
+    // only the output is still shared
+    pub fn isolated(&self) -> Self {
+        let mut modules = Modules::new();
+        modules.prelude = self.modules.borrow().prelude;
+        modules.projects = self.modules.borrow().projects.clone();
+        REnv {
+            debugger: Rc::new(RefCell::new(Debugger::new())),
+            tracer: Rc::new(RefCell::new(Tracer::new())),
+            profiler: Rc::new(RefCell::new(Profiler::new())),
+            tests: Rc::new(RefCell::new(Tests::new())),
+            modules: Rc::new(RefCell::new(modules)),
+            value_meta: Rc::new(RefCell::new(self.value_meta.borrow().clone())),
+            hooked: Rc::new(Cell::new(false)),
+            ..self.clone()
+        }
+    }
+    // called after turning the debugger, tracer or profiler on or off
+    pub fn update_hooks(&self) {
+        let on = self.debugger.borrow().enabled
+            || self.tracer.borrow().active()
+            || self.profiler.borrow().active();
+        self.hooked.set(on);
+    }
     pub fn def<S>(&mut self, key: S, val: RVal) -> RVal
     where
         S: Into<String>,
@@ -39,13 +128,49 @@ impl REnv {
         self.symbols.insert(key.into(), val.clone());
         val
     }
+    // binds a builtin and registers its documentation
+    pub fn defn(
+        &mut self,
+        key: &str,
+        f: fn(&[RVal], &mut REnv) -> RVal,
+        signature: &str,
+        doc: &str,
+    ) {
+        self.def(key, RBfn(f));
+        let d = RDoc {
+            signature: signature.to_string(),
+            doc: doc.to_string(),
+        };
+        Rc::make_mut(&mut self.docs).insert(key.to_string(), d);
+    }
     pub fn get<S>(&self, key: S) -> Option<RVal>
     where
         S: Copy + Into<String>,
     {
-        match self.symbols.get(&key.into()[..]) {
+        let key = key.into();
+        match self.symbols.get(&key[..]) {
             Some(v) => Some(v.clone()),
-            None => None,
+            None => self.scope.as_ref()?.get(&key[..]).cloned(),
+        }
+    }
+    // an environment for the body of a module's function, which sees the
+    // module's bindings instead of the caller's
+    pub fn in_scope(&self, scope: Rc<FnvHashMap<String, RVal>>) -> Self {
+        REnv {
+            symbols: FnvHashMap::default(),
+            scope: Some(scope),
+            readers: self.readers.clone(),
+            meta: self.meta.clone(),
+            docs: self.docs.clone(),
+            debugger: self.debugger.clone(),
+            tracer: self.tracer.clone(),
+            profiler: self.profiler.clone(),
+            tests: self.tests.clone(),
+            modules: self.modules.clone(),
+            output: self.output.clone(),
+            value_meta: self.value_meta.clone(),
+            hooked: self.hooked.clone(),
+            exit: self.exit.clone(),
         }
     }
     pub fn restore<S>(&mut self, key: S, val: Option<RVal>) where S: Into<String> {
@@ -57,7 +182,7 @@ impl REnv {
                     .into_iter()
                     .filter(|(k,_)| k != &ks[..])
                     .collect();
-                RLstArgs![vec![]]
+                RLstArgs![[]]
             }
         };
     }
@@ -67,9 +192,30 @@ impl REnv {
 ** @builtins
 ******************************************************************************/
 
+// forms handled by try_builtin rather than bound in the environment, with
+// their signatures and documentation
+pub const SPECIAL_FORMS: [(&str, &str, &str); 11] = [
+    ("at", "Int Vec", "get the nth element of a Vec"),
+    ("head", "(Lst | Vec)", "get the first element of a Lst or a Vec"),
+    ("rest", "(Lst | Vec)", "get a Lst or Vec without its first element"),
+    ("do", "Any ...", "evaluate several expressions in sequence, return the last one"),
+    ("let", "Sym Any", "bind the value of an expression to a symbol"),
+    ("fn", "Str? (Sym ...) Any", "make a function, with an optional docstring"),
+    ("quote", "Any", "return a value without evaluating it first"),
+    ("eval", "Any", "evaluate a string or a list"),
+    ("get", "Any Map", "get the value of a key in a Map"),
+    ("var", "Sym", "return the value bound to a symbol"),
+    ("defreader", "Sym Fn", "register a reader macro for #Sym"),
+];
+
+pub fn is_special_form(name: &str) -> bool {
+    SPECIAL_FORMS.iter().any(|(f, _, _)| *f == name)
+}
+
 impl REnv {
-    pub fn try_builtin(&mut self, x: &RVal, xs: &[RVal]) -> RVal {
-        match &x {
+    // None if x does not name a special form
+    pub fn try_builtin(&mut self, x: &RVal, xs: &[RVal]) -> Option<RVal> {
+        let res = match &x {
             _RSym(s) => match &s[..] {
                 "at" => self.builtin_at(xs),
                 "head" => self.builtin_head(xs),
@@ -80,28 +226,31 @@ impl REnv {
                 "quote" => self.builtin_quote(xs),
                 "eval" => self.builtin_eval(xs),
                 "get" => self.builtin_get(xs),
-                _ => RNil,
+                "var" => self.builtin_var(xs),
+                "defreader" => self.builtin_defreader(xs),
+                _ => return None,
             },
             RLst(vs) => {
                 if vs.is_empty() {
-                    return RErrExpected!("Sym", x.clone().variant());
+                    return Some(RErrExpected!("Sym", x.clone().variant()));
                 }
-                let new_val = eval(&x, self);
+                let new_val = eval(x, self);
                 match &new_val {
-                    RBfn(f) => f(xs, self),
-                    RLfn(lambda) => eval_lambda(lambda, &xs[..], self),
+                    RBfn(f) => eval_builtin(x, *f, xs, self),
+                    RLfn(lambda) => eval_lambda(x, lambda, xs, self),
                     _ => RErrExpected!("(Sym)", x.clone().variant()),
                 }
             }
             _ => RErrExpected!("(Sym)", x.clone().variant()),
-        }
+        };
+        Some(res)
     }
     fn builtin_get(&mut self, xs: &[RVal]) -> RVal {
         match xs.len() {
             2 => match eval(&xs[1], self) {
                 RMap(hm) => match hm.get(&eval(&xs[0], self)) {
                     Some(v) => v.clone(),
-                    None => RLstArgs!(vec![]),
+                    None => RLstArgs!([]),
                 },
                 _ => RErrExpected!("(Any Map)", RLstArgs![xs].variant()),
             },
@@ -130,7 +279,7 @@ impl REnv {
         match eval(&xs[0], self) {
             RLst(vs) | RVec(vs) => {
                 if vs.is_empty() {
-                    RLstArgs!(vec![])
+                    RLstArgs!([])
                 } else {
                     vs[0].clone()
                 }
@@ -145,14 +294,14 @@ impl REnv {
         match eval(&xs[0], self) {
             RLst(vs) => {
                 if vs.len() < 2 {
-                    RLstArgs!(vec![])
+                    RLstArgs!([])
                 } else {
                     RLstArgs!(&vs[1..])
                 }
             }
             RVec(vs) => {
                 if vs.len() < 2 {
-                    RLstArgs!(vec![])
+                    RLstArgs!([])
                 } else {
                     eval(&RVecArgs!(&vs[1..]), self)
                 }
@@ -166,7 +315,7 @@ impl REnv {
             return RErrExpected!("(Any)", RLstArgs![xs].variant());
         }
         for v in xs[..].iter() {
-            val = eval(&v, self);
+            val = eval(v, self);
             if let _RErr(_) = val.clone() {
                 return val.clone();
             }
@@ -174,25 +323,54 @@ impl REnv {
         val
     }
     pub fn builtin_def(&mut self, xs: &[RVal]) -> RVal {
-        match xs.len() {
-            2 => match &xs[0] {
-                _RSym(s) => {
-                    let new_val = eval(&xs[1], self);
-                    self.def(&s[..], new_val)
+        if xs.len() != 2 {
+            return RErrExpected!("(Sym Any)", RLstArgs![xs].variant());
+        }
+        // (let ^meta name value) and (let name ^meta value)
+        let (name, name_meta) = match split_meta(&xs[0]) {
+            Some((name, m)) => (name, eval(m, self)),
+            None => (&xs[0], RNil),
+        };
+        let s = match name {
+            _RSym(s) => s.clone(),
+            _ => return RErrExpected!("(Sym Any)", RLstArgs![xs].variant()),
+        };
+        let (new_val, val_meta) = match split_meta(&xs[1]) {
+            Some((form, m)) => match (eval(form, self), eval(m, self)) {
+                (v @ (RLfn(_) | RLst(_) | RVec(_) | RMap(_) | _RSym(_)), m) => {
+                    (attach_meta(v, m, self), RNil)
                 }
-                _ => RErrExpected!("(Sym Any)", RLstArgs![xs].variant()),
+                (v, m @ RMap(_)) => (v, m),
+                (_, m) => return RErrExpected!("Map", m.variant()),
             },
-            _ => RErrExpected!("(Sym Any)", RLstArgs![xs].variant()),
+            None => (eval(&xs[1], self), RNil),
+        };
+        match merge_meta(&name_meta, &val_meta) {
+            m @ RMap(_) => {
+                self.meta.insert(s.to_string(), m);
+            }
+            RNil => {
+                self.meta.remove(&s[..]);
+            }
+            m => return RErrExpected!("Map", m.variant()),
         }
+        self.def(&s[..], new_val)
     }
     fn builtin_lfn(&mut self, xs: &[RVal]) -> RVal {
+        // (fn "docstring" (parameters) body)
+        let (meta, xs) = match xs {
+            [_RStr(_), _, _] => (RMapArgs!([RSym(":doc"), xs[0].clone()]), &xs[1..]),
+            _ => (RNil, xs),
+        };
         match xs.len() {
             2 => match &xs[0] {
                 RLst(ps) => {
                     if REnv::are_symbols(&ps[..]) {
-                        RLfn(Arc::new(RLambda {
-                            params: Arc::new(xs[0].clone()),
-                            body: Arc::new(xs[1].clone()),
+                        RLfn(Rc::new(RLambda {
+                            params: Rc::new(xs[0].clone()),
+                            body: Rc::new(xs[1].clone()),
+                            meta: Rc::new(meta),
+                            module: None,
                         }))
                     } else {
                         RErr("parameters must be symbols")
@@ -214,22 +392,50 @@ impl REnv {
     }
     pub fn is_function(&self, x: &RVal) -> RVal {
         match &x {
-            _RSym(s) => {
-                let v = self.symbols.get(&s.to_string());
-                match v {
-                    Some(v) => v.clone(),
-                    None => RNil,
-                }
-            }
+            _RSym(s) => self.get(&s[..]).unwrap_or(RNil),
             _ => RNil,
         }
     }
     fn builtin_quote(&mut self, xs: &[RVal]) -> RVal {
         match xs.len() {
-            1 => xs[0].clone(),
+            1 => quote_meta(&xs[0], self).unwrap_or_else(|| xs[0].clone()),
             _ => RErrExpected!("(Any)", RLstArgs!(xs).variant()),
         }
     }
+    fn builtin_var(&mut self, xs: &[RVal]) -> RVal {
+        match xs {
+            [_RSym(s)] => match self.get(&s[..]) {
+                Some(v) => v,
+                None => RErrUnboundSymbol!(s),
+            },
+            _ => RErrExpected!("(Sym)", RLstArgs!(xs).variant()),
+        }
+    }
+    fn builtin_defreader(&mut self, xs: &[RVal]) -> RVal {
+        match xs {
+            [_RSym(tag), f] => match eval(f, self) {
+                f @ RBfn(_) | f @ RLfn(_) => {
+                    self.readers.insert(tag.to_string(), f.clone());
+                    f
+                }
+                _ => RErrExpected!("(Sym Fn)", RLstArgs!(xs).variant()),
+            },
+            _ => RErrExpected!("(Sym Fn)", RLstArgs!(xs).variant()),
+        }
+    }
+    // the expansion of #tag form, from the reader registered for tag
+    pub fn read_tagged(&mut self, tag: &str, form: &RVal) -> RVal {
+        let reader = match self.readers.get(tag) {
+            Some(f) => f.clone(),
+            None => return RErr(format!("no reader macro for #{}", tag)),
+        };
+        let arg = RLstArgs!([RSym("quote"), form.clone()]);
+        match &reader {
+            RBfn(f) => eval_builtin(&RSym(format!("#{}", tag)), *f, &[arg], self),
+            RLfn(lambda) => eval_lambda(&RSym(format!("#{}", tag)), lambda, &[arg], self),
+            _ => RErr("internal error (dispatch)"),
+        }
+    }
     fn builtin_eval(&mut self, xs: &[RVal]) -> RVal {
         match xs.len() {
             1 => match &xs[0] {
@@ -249,17 +455,47 @@ impl REnv {
 ** @repl io
 ******************************************************************************/
 
+// extensions tried in order when loading a path that does not exist as given
+pub const EXTENSIONS: [&str; 2] = ["risp", "rs"];
+
+// the file a path given to load refers to
+pub fn resolve(path: &str) -> Option<String> {
+    if Path::new(path).is_file() {
+        return Some(path.to_string());
+    }
+    EXTENSIONS
+        .iter()
+        .map(|ext| format!("{}.{}", path, ext))
+        .find(|p| Path::new(p).is_file())
+}
+
 impl REnv {
+    // evaluates every form in src, stopping at the first error
+    pub fn eval_source<S>(&mut self, src: S) -> RVal
+    where
+        S: Into<String>,
+    {
+        match read_all(src) {
+            Ok(forms) => {
+                let mut res = RNil;
+                for form in forms.iter() {
+                    res = eval_read(form, self);
+                    if let _RErr(_) = res {
+                        break;
+                    }
+                }
+                res
+            }
+            Err(e) => e,
+        }
+    }
     pub fn load<S>(&mut self, path: S) -> RVal
     where
         S: Into<String>,
     {
-        let new_path = path.into();
-        if let Ok(src) = fs::read_to_string(&new_path) {
-            let new_src = vec!["(do\n", &src[..], ")"].join("");
-            rep(new_src, self)
-        } else {
-            RErr(format!("could not load {}", &new_path))
+        match read_module(&path.into(), self) {
+            Ok((_, src, path)) => eval_file(src, path, self),
+            Err(e) => e,
         }
     }
 }
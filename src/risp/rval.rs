@@ -5,9 +5,9 @@
 use fnv::FnvHashMap;
 
 use std::fmt;
-use std::sync::Arc;
+use std::rc::Rc;
 
-use crate::risp::REnv;
+use crate::risp::{Port, REnv};
 
 /******************************************************************************
 ** @base data types
@@ -15,24 +15,29 @@ use crate::risp::REnv;
 
 #[derive(Clone)]
 pub enum RVal {
-    _RErr(Arc<String>),
-    _RStr(Arc<String>),
-    _RSym(Arc<String>),
+    _RErr(Rc<String>),
+    _RStr(Rc<String>),
+    _RSym(Rc<String>),
     RNil,
     RBool(bool),
     RFlt(f64),
     RInt(i64),
-    RLst(Arc<Vec<RVal>>),
-    RVec(Arc<Vec<RVal>>),
-    RMap(Arc<FnvHashMap<RVal, RVal>>),
+    RLst(Rc<Vec<RVal>>),
+    RVec(Rc<Vec<RVal>>),
+    RMap(Rc<FnvHashMap<RVal, RVal>>),
     RBfn(fn(&[RVal], &mut REnv) -> RVal),
-    RLfn(Arc<RLambda>),
+    RLfn(Rc<RLambda>),
+    RPort(Rc<Port>),
 }
 
 #[derive(Clone)]
 pub struct RLambda {
-    pub params: Arc<RVal>,
-    pub body: Arc<RVal>,
+    pub params: Rc<RVal>,
+    pub body: Rc<RVal>,
+    pub meta: Rc<RVal>,
+    // bindings of the module that exported the function, which it is
+    // evaluated in instead of the caller's
+    pub module: Option<Rc<FnvHashMap<String, RVal>>>,
 }
 
 /******************************************************************************
@@ -47,7 +52,7 @@ macro_rules! rval_impl_s {
         where
             S: Into<String>,
         {
-            $_rty(std::sync::Arc::new(s.into()))
+            $_rty(std::rc::Rc::new(s.into()))
         }
     };
 }
@@ -84,7 +89,6 @@ macro_rules! RErrUnboundSymbol {
 macro_rules! RMapArgs {
     ($args: expr) => {
         if !$args.is_empty() && $args.len() % 2 == 0 {
-            let mut map = fnv::FnvHashMap::default();
             let mut ks = vec![];
             let mut vs = vec![];
             let mut i = 0;
@@ -111,10 +115,7 @@ macro_rules! RMapArgs {
                 i += 1;
             }
             if hashable {
-                for (k, v) in ks.iter().zip(vs.iter()) {
-                    map.insert(k.clone(), v.clone());
-                }
-                RMap(std::sync::Arc::new(map))
+                RMap(std::rc::Rc::new(ks.into_iter().zip(vs).collect()))
             } else {
                 RErr(format!("{} is not hashable", var))
             }
@@ -127,14 +128,14 @@ macro_rules! RMapArgs {
 #[allow(non_snake_case)]
 macro_rules! RLstArgs {
     ($args: expr) => {
-        RLst(std::sync::Arc::new($args.to_vec()))
+        RLst(std::rc::Rc::new($args.to_vec()))
     };
 }
 
 #[allow(non_snake_case)]
 macro_rules! RVecArgs {
     ($args: expr) => {
-        RVec(std::sync::Arc::new($args.to_vec()))
+        RVec(std::rc::Rc::new($args.to_vec()))
     };
 }
 
@@ -157,31 +158,65 @@ impl RVal {
 ** @output
 ******************************************************************************/
 
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+        .replace('\0', "\\0")
+}
+
+// lists read from reader shorthand are printed back the same way
+pub fn reader_prefix(vs: &[RVal]) -> Option<String> {
+    match vs {
+        [RVal::_RSym(s), _] => match &s[..] {
+            "quote" => Some("'".to_string()),
+            "var" => Some("#'".to_string()),
+            _ if s.len() > 1 && s.starts_with('#') => Some(format!("{} ", s)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// the alternate form, {:#}, escapes strings so they read back as written
 impl fmt::Display for RVal {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use RVal::*;
+        let alt = f.alternate();
+        let show = |x: &RVal| if alt { format!("{:#}", x) } else { x.to_string() };
         let s = match self {
             _RErr(e) => format!("(Err: {})", e),
+            _RStr(s) if alt => format!("\"{}\"", escape(s)),
             _RStr(s) => format!("\"{}\"", s),
             _RSym(s) => s.to_string(),
             RNil => "nil".to_string(),
             RBool(b) => b.to_string(),
             RFlt(f) => f.to_string(),
             RInt(i) => i.to_string(),
-            RLst(vs) => {
-                let xs: Vec<String> = vs.iter().map(|x| x.to_string()).collect();
-                format!("({})", xs.join(" "))
-            }
+            RLst(vs) => match reader_prefix(vs) {
+                Some(p) => format!("{}{}", p, show(&vs[1])),
+                None => {
+                    let xs: Vec<String> = vs.iter().map(show).collect();
+                    format!("({})", xs.join(" "))
+                }
+            },
             RMap(vs) => {
-                let xs: Vec<String> = vs.iter().map(|(x, y)| format!("{} {}", x, y)).collect();
+                let xs: Vec<String> = vs.iter().map(|(x, y)| format!("{} {}", show(x), show(y))).collect();
                 format!("{{{}}}", xs.join(" "))
             }
             RVec(vs) => {
-                let xs: Vec<String> = vs.iter().map(|x| x.to_string()).collect();
+                let xs: Vec<String> = vs.iter().map(show).collect();
                 format!("[{}]", xs.join(" "))
             }
             RBfn(_) => "Builtin-Fn".to_string(),
-            RLfn(l) => format!("(Fn {} {})", l.params, l.body),
+            RLfn(l) => format!("(Fn {} {})", show(&l.params), show(&l.body)),
+            RPort(p) => {
+                let dir = if p.input { "input" } else { "output" };
+                let closed = if p.is_closed() { "closed " } else { "" };
+                format!("#<{}{}-port {}>", closed, dir, p.name)
+            }
         };
         write!(f, "{}", s)
     }
@@ -215,6 +250,7 @@ impl RVal {
             }
             RBfn(_) => "Builtin-Fn".to_string(),
             RLfn(_) => "Fn".to_string(),
+            RPort(_) => "Port".to_string(),
         }
     }
 }
@@ -0,0 +1,44 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use std::env;
+use std::fs;
+
+use crate::risp::resolve;
+
+/******************************************************************************
+** @standard library
+******************************************************************************/
+
+// the standard library is built into the binary, so risp runs from any
+// directory, and loaded through paths starting with stdlib/
+const STDLIB: [(&str, &str); 4] = [
+    ("prelude", include_str!("../../stdlib/prelude.risp")),
+    ("logic", include_str!("../../stdlib/logic.risp")),
+    ("list", include_str!("../../stdlib/list.risp")),
+    ("math", include_str!("../../stdlib/math.risp")),
+];
+
+pub const STDLIB_PREFIX: &str = "stdlib/";
+
+// a directory to load the standard library from instead, to work on it
+// without rebuilding risp
+pub const STDLIB_VAR: &str = "RISP_STDLIB";
+
+// the source of a standard library module, None if path is not one
+pub fn stdlib_source(path: &str) -> Option<String> {
+    let name = path.strip_prefix(STDLIB_PREFIX)?;
+    let name = name
+        .strip_suffix(".risp")
+        .or_else(|| name.strip_suffix(".rs"))
+        .unwrap_or(name);
+    if let Some(dir) = env::var_os(STDLIB_VAR) {
+        let path = format!("{}/{}", dir.to_string_lossy(), name);
+        return resolve(&path).and_then(|p| fs::read_to_string(p).ok());
+    }
+    STDLIB
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, src)| src.to_string())
+}
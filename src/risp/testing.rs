@@ -0,0 +1,180 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use std::cmp::Ordering;
+
+use crate::risp::{doc_examples, doc_of, eval, eval_read, read_all, REnv, RErr, RSym, RVal, RVal::*};
+
+/******************************************************************************
+** @tests
+******************************************************************************/
+
+pub struct Tests {
+    // name and body of every deftest, in order
+    pub defined: Vec<(String, RVal)>,
+    failures: Vec<String>,
+}
+
+impl Tests {
+    pub fn new() -> Self {
+        Tests {
+            defined: vec![],
+            failures: vec![],
+        }
+    }
+}
+
+// runs the body of a test, returning why it failed if it did
+pub fn run_test(body: &RVal, env: &REnv) -> Vec<String> {
+    env.tests.borrow_mut().failures.clear();
+    let res = eval(body, &mut env.clone());
+    let mut failures: Vec<String> = env.tests.borrow_mut().failures.drain(..).collect();
+    if let _RErr(_) = res {
+        if failures.is_empty() {
+            failures.push(res.to_string());
+        }
+    }
+    failures
+}
+
+// runs the examples in the docstring of name, returning the ones that did not
+// evaluate to the documented value, or None if there are no examples
+pub fn run_doc_test(name: &str, env: &REnv) -> Option<Vec<String>> {
+    let examples = doc_examples(&doc_of(name, env)?.doc);
+    if examples.is_empty() {
+        return None;
+    }
+    let mut failures = vec![];
+    for (src, expected) in examples {
+        let actual = match read_all(&src[..]) {
+            Ok(forms) => {
+                let mut env = env.clone();
+                forms.iter().fold(RNil, |_, form| eval_read(form, &mut env))
+            }
+            Err(e) => e,
+        };
+        let matches = match read_all(&expected[..]) {
+            Ok(vs) => vs.len() == 1 && vs[0] == actual,
+            Err(_) => false,
+        };
+        if !matches && actual.to_string() != expected {
+            failures.push(format!(
+                "{}\n  expected: {}\n  actual:   {}",
+                src, expected, actual
+            ));
+        }
+    }
+    Some(failures)
+}
+
+// where two values first differ inside nested lists or vectors
+fn difference(expected: &RVal, actual: &RVal) -> Option<String> {
+    let (xs, ys) = match (expected, actual) {
+        (RLst(xs), RLst(ys)) | (RVec(xs), RVec(ys)) => (xs, ys),
+        _ => return None,
+    };
+    for (i, (x, y)) in xs.iter().zip(ys.iter()).enumerate() {
+        if x != y {
+            return match difference(x, y) {
+                Some(d) => Some(format!("[{}]{}", i, d)),
+                None => Some(format!("[{}]: expected {}, actual {}", i, x, y)),
+            };
+        }
+    }
+    match xs.len().cmp(&ys.len()) {
+        Ordering::Equal => None,
+        _ => Some(format!(
+            ": expected {} elements, actual {}",
+            xs.len(),
+            ys.len()
+        )),
+    }
+}
+
+// records a failed assertion, which is also returned as an error
+fn fail(form: RVal, msg: String, env: &REnv) -> RVal {
+    let failure = format!("{}\n  {}", form, msg.replace('\n', "\n  "));
+    env.tests.borrow_mut().failures.push(failure.clone());
+    RErr(format!("assertion failed: {}", failure))
+}
+
+/******************************************************************************
+** @tests into environment
+******************************************************************************/
+
+pub fn load_testing(env: &mut REnv) {
+    env.defn("deftest", deftest, "Sym Any ...", "define a test run by risp test");
+    env.defn("is", is, "Any", "assert that an expression is true");
+    env.defn(
+        "assert=",
+        assert_eq,
+        "Any Any",
+        "assert that an expression evaluates to the expected value",
+    );
+    env.defn(
+        "assert-err",
+        assert_err,
+        "Any",
+        "assert that an expression evaluates to an error",
+    );
+}
+
+fn deftest(args: &[RVal], env: &mut REnv) -> RVal {
+    match args {
+        [name @ _RSym(s), body @ ..] if !body.is_empty() => {
+            let mut forms = vec![RSym("do")];
+            forms.extend(body.iter().cloned());
+            let mut tests = env.tests.borrow_mut();
+            tests.defined.retain(|(n, _)| n != &s[..]);
+            tests.defined.push((s.to_string(), RLstArgs!(forms)));
+            name.clone()
+        }
+        _ => RErrExpected!("(Sym Any ...)", RLstArgs![args].variant()),
+    }
+}
+
+fn is(args: &[RVal], env: &mut REnv) -> RVal {
+    match args {
+        [x] => match eval(x, env) {
+            RBool(true) => RBool(true),
+            v => {
+                let form = RLstArgs!([RSym("is"), x.clone()]);
+                fail(form, format!("expected true, actual {}", v), env)
+            }
+        },
+        _ => RErrExpected!("(Any)", RLstArgs![args].variant()),
+    }
+}
+
+fn assert_eq(args: &[RVal], env: &mut REnv) -> RVal {
+    match args {
+        [x, y] => {
+            let expected = eval(x, env);
+            let actual = eval(y, env);
+            if expected == actual {
+                return RBool(true);
+            }
+            let mut msg = format!("expected: {}\nactual:   {}", expected, actual);
+            if let Some(d) = difference(&expected, &actual) {
+                msg.push_str(&format!("\nfirst difference at {}", d));
+            }
+            let form = RLstArgs!([RSym("assert="), x.clone(), y.clone()]);
+            fail(form, msg, env)
+        }
+        _ => RErrExpected!("(Any Any)", RLstArgs![args].variant()),
+    }
+}
+
+fn assert_err(args: &[RVal], env: &mut REnv) -> RVal {
+    match args {
+        [x] => match eval(x, env) {
+            _RErr(_) => RBool(true),
+            v => {
+                let form = RLstArgs!([RSym("assert-err"), x.clone()]);
+                fail(form, format!("expected an error, actual {}", v), env)
+            }
+        },
+        _ => RErrExpected!("(Any)", RLstArgs![args].variant()),
+    }
+}
@@ -0,0 +1,116 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use fnv::FnvHashSet;
+
+use crate::risp::{REnv, RErr, RSym, RVal, RVal::*};
+
+/******************************************************************************
+** @tracing
+******************************************************************************/
+
+pub struct Tracer {
+    names: FnvHashSet<String>,
+    // trace every call, as with --trace
+    pub all: bool,
+    depth: usize,
+}
+
+impl Tracer {
+    pub fn new() -> Self {
+        Tracer {
+            names: FnvHashSet::default(),
+            all: false,
+            depth: 0,
+        }
+    }
+    pub fn active(&self) -> bool {
+        self.all || !self.names.is_empty()
+    }
+    fn traces(&self, name: &RVal) -> bool {
+        match name {
+            _ if self.all => true,
+            _RSym(s) => self.names.contains(&s[..]),
+            _ => false,
+        }
+    }
+}
+
+// logs a call to stderr if it is traced, returning whether it was; builtins
+// are logged with their arguments unevaluated, lambdas with their values
+pub fn trace_call(name: &RVal, args: &[RVal], env: &REnv) -> bool {
+    let mut tracer = env.tracer.borrow_mut();
+    if !tracer.traces(name) {
+        return false;
+    }
+    let mut vs = vec![name.clone()];
+    vs.extend(args.iter().cloned());
+    let _ = env.write_err(&format!("{}{}\n", "  ".repeat(tracer.depth), RLstArgs!(vs)));
+    tracer.depth += 1;
+    true
+}
+
+pub fn trace_return(val: &RVal, env: &REnv) {
+    let mut tracer = env.tracer.borrow_mut();
+    tracer.depth -= 1;
+    let _ = env.write_err(&format!("{}=> {}\n", "  ".repeat(tracer.depth), val));
+}
+
+/******************************************************************************
+** @tracing into environment
+******************************************************************************/
+
+pub fn load_trace(env: &mut REnv) {
+    env.defn(
+        "trace",
+        trace,
+        "Sym ...",
+        "log calls to functions to stderr, list the traced functions",
+    );
+    env.defn(
+        "untrace",
+        untrace,
+        "Sym ...",
+        "stop tracing functions, or all of them",
+    );
+}
+
+fn traced(env: &REnv) -> RVal {
+    let mut names: Vec<String> = env.tracer.borrow().names.iter().cloned().collect();
+    names.sort();
+    RLstArgs!(names.into_iter().map(RSym).collect::<Vec<RVal>>())
+}
+
+fn trace(args: &[RVal], env: &mut REnv) -> RVal {
+    for x in args.iter() {
+        match x {
+            _RSym(s) => match env.get(&s[..]) {
+                Some(RBfn(_)) | Some(RLfn(_)) => {
+                    env.tracer.borrow_mut().names.insert(s.to_string());
+                }
+                Some(v) => return RErrExpected!("Fn", v.variant()),
+                None => return RErrUnboundSymbol!(s),
+            },
+            _ => return RErrExpected!("(Sym ...)", RLstArgs![args].variant()),
+        }
+    }
+    env.update_hooks();
+    traced(env)
+}
+
+fn untrace(args: &[RVal], env: &mut REnv) -> RVal {
+    if args.is_empty() {
+        env.tracer.borrow_mut().names.clear();
+    }
+    for x in args.iter() {
+        match x {
+            _RSym(s) => {
+                env.tracer.borrow_mut().names.remove(&s[..]);
+            }
+            _ => return RErrExpected!("(Sym ...)", RLstArgs![args].variant()),
+        }
+    }
+    env.update_hooks();
+    traced(env)
+}
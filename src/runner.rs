@@ -0,0 +1,136 @@
+/******************************************************************************
+** @crates and modules
+******************************************************************************/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use fnv::FnvHashSet;
+
+use crate::repl::prelude;
+use crate::risp::{
+    documented, relative, run_doc_test, run_test, Project, REnv, RVal::*, MANIFEST,
+};
+
+const TEST_DIR: &str = "test";
+const TEST_SUFFIXES: [&str; 2] = ["_test.risp", "_test.rs"];
+
+/******************************************************************************
+** @test runner
+******************************************************************************/
+
+#[derive(Default)]
+struct Summary {
+    passed: usize,
+    failed: usize,
+}
+
+impl Summary {
+    fn report(&mut self, name: &str, failures: Vec<String>) {
+        if failures.is_empty() {
+            println!("  ok   {}", name);
+            self.passed += 1;
+            return;
+        }
+        println!("  FAIL {}", name);
+        for f in failures {
+            println!("       {}", f.replace('\n', "\n       "));
+        }
+        self.failed += 1;
+    }
+    // the examples in the docstrings of the given functions
+    fn doc_tests(&mut self, names: &[String], env: &REnv) {
+        for name in names {
+            if let Some(failures) = run_doc_test(name, env) {
+                self.report(&format!("doc {}", name), failures);
+            }
+        }
+    }
+}
+
+// test files under a directory, or the path itself if it is a file
+fn discover(path: &Path, found: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        found.push(path.to_path_buf());
+        return;
+    }
+    let mut entries: Vec<PathBuf> = match fs::read_dir(path) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(_) => return,
+    };
+    entries.sort();
+    for entry in entries {
+        let is_test = entry
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| TEST_SUFFIXES.iter().any(|s| n.ends_with(s)));
+        // another project's tests belong to that project
+        let nested = entry.is_dir() && entry.join(MANIFEST).is_file();
+        if (entry.is_dir() && !nested) || is_test {
+            discover(&entry, found);
+        }
+    }
+}
+
+/// Runs the examples in the docstrings of the standard library, then the
+/// tests defined with deftest in every file named by paths, or in every
+/// *_test.risp file under test/, or under the test and source directories of
+/// the project if there is one, each file in a fresh environment and each test
+/// in its own copy of it. Examples in the docstrings of functions a file
+/// defines are run with its tests. Returns the exit status: non-zero if any
+/// test failed or a file could not be loaded.
+pub fn run(paths: &[String], projects: &[Project]) -> i32 {
+    let mut files = vec![];
+    match projects.first() {
+        Some(p) if paths.is_empty() => {
+            let dirs = std::iter::once(p.root.join(TEST_DIR)).chain(p.source_dirs.iter().cloned());
+            for dir in dirs.filter(|d| d.is_dir()) {
+                discover(&dir, &mut files);
+            }
+        }
+        None if paths.is_empty() => discover(Path::new(TEST_DIR), &mut files),
+        _ => (),
+    }
+    for path in paths {
+        discover(Path::new(path), &mut files);
+    }
+    let stdlib = match prelude() {
+        Ok(env) => env,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    stdlib.modules.borrow_mut().projects = projects.to_vec();
+    let mut summary = Summary::default();
+    println!("stdlib");
+    summary.doc_tests(&documented(&stdlib), &stdlib);
+    let baseline: FnvHashSet<String> = stdlib.symbols.keys().cloned().collect();
+    for file in files {
+        let mut env = stdlib.isolated();
+        println!("{}", relative(&file));
+        if let e @ _RErr(_) = env.load(file.to_string_lossy()) {
+            println!("  FAIL {}", e);
+            summary.failed += 1;
+            continue;
+        }
+        let tests = env.tests.borrow().defined.clone();
+        for (name, body) in tests.iter() {
+            summary.report(name, run_test(body, &env));
+        }
+        let mut defined: Vec<String> = env
+            .symbols
+            .keys()
+            .filter(|k| !baseline.contains(*k))
+            .cloned()
+            .collect();
+        defined.sort();
+        summary.doc_tests(&defined, &env);
+    }
+    println!("\n{} passed, {} failed", summary.passed, summary.failed);
+    if summary.failed > 0 {
+        1
+    } else {
+        0
+    }
+}
@@ -1,7 +0,0 @@
-(load stdlib/logic.rs)
-(load stdlib/list.rs)
-(load stdlib/math.rs)
-
-(let null?
-  (fn (x)
-    (= () x)))
\ No newline at end of file
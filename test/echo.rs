@@ -1,5 +0,0 @@
-(let main
-  (fn () (do
-    (let input (read))
-    (write input "\n"))))
-(main)
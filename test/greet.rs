@@ -1,6 +0,0 @@
-(let main
-  (fn () (do
-    (let name (read "Please tell me your name: "))
-    (write "Hello, " name "!\n"))))
-
-(main)
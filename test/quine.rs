@@ -1,2 +0,0 @@
-;; ./risp < quine.rs
-((fn (x) (cons x (cons (quote quote) x))) (quote (fn (x) (cons x (cons (quote quote) x)))))
@@ -1,6 +0,0 @@
-(let main
-  (fn () (do
-    (let the-answer 42)
-    (write "the answer is " the-answer "\n"))))
-
-(main)
@@ -0,0 +1,298 @@
+//! Runs every script in test/ and compares what it prints and its exit status
+//! to the expectation checked in next to it as name.out. A name.in file next
+//! to a script is fed to it on stdin. A name.args file runs risp with the
+//! arguments it lists, one per line, instead of the script, from the
+//! directory named in name.cwd if there is one, and with the environment
+//! variables set in name.env, one NAME=value per line.
+//!
+//! After an intended change in output, regenerate the expectations with
+//! `BLESS=1 cargo test --test scripts` and review the diff.
+//!
+//! Every risp source under stdlib/ and test/ must also be left unchanged by
+//! `risp fmt`. Output that depends on timing, like :time and the profiler,
+//! is checked by its shape instead.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const TEST_DIR: &str = "test";
+
+fn scripts() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(TEST_DIR);
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .expect("could not read test/")
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|x| x == "risp" || x == "rs" || x == "args")
+        })
+        .collect();
+    // a script with a name.args file is run through it
+    paths.retain(|p| {
+        p.extension().is_some_and(|x| x == "args") || !p.with_extension("args").exists()
+    });
+    paths.sort();
+    paths
+}
+
+// every .risp file under dir
+fn sources(dir: &Path, found: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            sources(&path, found);
+        } else if path.extension().is_some_and(|x| x == "risp") {
+            found.push(path);
+        }
+    }
+}
+
+// the exit status, stdout and stderr of risp run with the given arguments
+// and stdin, for checks that do not fit a .out file
+fn risp(args: &[&str], stdin: &[u8]) -> (Option<i32>, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_risp"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("could not run risp");
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    let out = child.wait_with_output().unwrap();
+    (
+        out.status.code(),
+        String::from_utf8_lossy(&out.stdout).to_string(),
+        String::from_utf8_lossy(&out.stderr).to_string(),
+    )
+}
+
+// stdout, stderr and exit status of running a script, in the format of the
+// .out files
+fn run(script: &Path) -> String {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let stdin = fs::read(script.with_extension("in")).unwrap_or_default();
+    let args: Vec<String> = match fs::read_to_string(script.with_extension("args")) {
+        Ok(args) => args.lines().map(String::from).collect(),
+        Err(_) => vec![script.strip_prefix(root).unwrap().display().to_string()],
+    };
+    let cwd = match fs::read_to_string(script.with_extension("cwd")) {
+        Ok(dir) => root.join(dir.trim()),
+        Err(_) => root.to_path_buf(),
+    };
+    let vars: Vec<(String, String)> = fs::read_to_string(script.with_extension("env"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| l.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_risp"))
+        .args(&args)
+        .envs(vars)
+        .current_dir(cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("could not run risp");
+    // risp may exit without reading all of its input
+    let _ = child.stdin.take().unwrap().write_all(&stdin);
+    let out = child.wait_with_output().unwrap();
+    format!(
+        "--- stdout\n{}--- stderr\n{}--- status\n{}\n",
+        String::from_utf8_lossy(&out.stdout),
+        String::from_utf8_lossy(&out.stderr),
+        out.status.code().map_or("signal".to_string(), |c| c.to_string())
+    )
+}
+
+#[test]
+fn scripts_match_expected_output() {
+    let bless = env::var_os("BLESS").is_some();
+    let mut failed = vec![];
+    for script in scripts() {
+        let actual = run(&script);
+        let expected_path = script.with_extension("out");
+        if bless {
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+        if actual != expected {
+            eprintln!(
+                "{}: output differs\n=== expected\n{}=== actual\n{}",
+                script.display(),
+                expected,
+                actual
+            );
+            failed.push(script.display().to_string());
+        }
+    }
+    assert!(failed.is_empty(), "unexpected output from {:?}", failed);
+}
+
+#[test]
+fn sources_are_formatted() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut paths = vec![];
+    sources(&root.join("stdlib"), &mut paths);
+    sources(&root.join(TEST_DIR), &mut paths);
+    let args: Vec<&str> = paths.iter().map(|p| p.to_str().unwrap()).collect();
+    let (status, out, _) = risp(&[&["fmt", "--check"], &args[..]].concat(), b"");
+    assert_eq!(status, Some(0), "run risp fmt on these files:\n{}", out);
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let src = fs::read(root.join(TEST_DIR).join("fmt.in")).unwrap();
+    let (status, once, _) = risp(&["fmt"], &src);
+    assert_eq!(status, Some(0));
+    let (status, twice, _) = risp(&["fmt"], once.as_bytes());
+    assert_eq!(status, Some(0));
+    assert_eq!(once, twice);
+}
+
+#[test]
+fn repl_time_shows_the_value_and_the_duration() {
+    let (status, out, _) = risp(&["-i"], b":time (+ 1 2)\n");
+    assert_eq!(status, Some(0));
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", out);
+    assert_eq!(lines[0], "3");
+    assert!(
+        lines[1].starts_with("; ") && lines[1].ends_with('s'),
+        "{}",
+        out
+    );
+}
+
+// the calls and function columns of a profile report, without the timings
+fn profiled(report: &str) -> Vec<(String, String)> {
+    let mut lines = report.lines();
+    let header: Vec<&str> = lines.next().unwrap_or("").split_whitespace().collect();
+    assert_eq!(
+        header,
+        ["calls", "inclusive", "exclusive", "function"],
+        "{}",
+        report
+    );
+    lines
+        .map(|line| {
+            let cols: Vec<&str> = line.split_whitespace().collect();
+            assert_eq!(cols.len(), 4, "{}", report);
+            assert!(
+                cols[1].ends_with("ms") && cols[2].ends_with("ms"),
+                "{}",
+                report
+            );
+            (cols[0].to_string(), cols[3].to_string())
+        })
+        .collect()
+}
+
+// the stacks of a folded file, checking each has a time in microseconds
+fn folded_stacks(path: &Path) -> Vec<String> {
+    let folded = fs::read_to_string(path).unwrap();
+    folded
+        .lines()
+        .map(|line| {
+            let (stack, micros) = line.rsplit_once(' ').unwrap();
+            assert!(micros.parse::<u64>().is_ok(), "{}", folded);
+            stack.to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn profile_flag_reports_every_call() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("profile_flag.folded");
+    let flag = format!("--profile={}", path.display());
+    let args = [
+        &flag[..],
+        "-e",
+        "(let sq (fn (x) (* x x)))",
+        "-e",
+        "(sq (sq 2))",
+    ];
+    let (status, out, err) = risp(&args, b"");
+    assert_eq!(status, Some(0));
+    assert_eq!(out, "(Fn (x) (* x x))\n16\n");
+    let mut calls = profiled(&err);
+    calls.sort();
+    let expected = [("2", "*"), ("2", "sq")];
+    assert_eq!(calls, expected.map(|(c, f)| (c.to_string(), f.to_string())));
+    assert_eq!(folded_stacks(&path), ["sq", "sq;*"]);
+}
+
+#[test]
+fn profile_is_reported_when_the_program_exits() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("profile_exit.folded");
+    let _ = fs::remove_file(&path);
+    let flag = format!("--profile={}", path.display());
+    let args = [&flag[..], "-e", "(let f (fn () (exit 3)))", "-e", "(f)"];
+    let (status, out, err) = risp(&args, b"");
+    assert_eq!(status, Some(3));
+    assert_eq!(out, "(Fn () (exit 3))\n");
+    let mut calls = profiled(&err);
+    calls.sort();
+    let expected = [("1", "exit"), ("1", "f")];
+    assert_eq!(calls, expected.map(|(c, f)| (c.to_string(), f.to_string())));
+    assert_eq!(folded_stacks(&path), ["f", "f;exit"]);
+}
+
+#[test]
+fn profile_builtin_reports_only_its_expression() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("profile_builtin.folded");
+    let src = format!(
+        "(let sq (fn (x) (* x x)))\n(sq 1)\n(profile (sq 3) \"{}\")\n",
+        path.display()
+    );
+    let (status, out, err) = risp(&["-"], src.as_bytes());
+    assert_eq!(status, Some(0));
+    assert_eq!(out, "");
+    let mut calls = profiled(&err);
+    calls.sort();
+    let expected = [("1", "*"), ("1", "sq")];
+    assert_eq!(calls, expected.map(|(c, f)| (c.to_string(), f.to_string())));
+    assert_eq!(folded_stacks(&path), ["sq", "sq;*"]);
+}
+
+#[test]
+fn stdlib_loads_from_risp_stdlib() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("stdlib");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("prelude.risp"), "(let answer 42)\n").unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_risp"))
+        .args(["-e", "answer", "-e", "(null? '())"])
+        .env("RISP_STDLIB", &dir)
+        .output()
+        .expect("could not run risp");
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "42\n");
+    assert_eq!(out.status.code(), Some(1));
+}
+
+#[cfg(unix)]
+#[test]
+fn walks_do_not_follow_links_to_directories() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("walk");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::write(dir.join("sub/a.risp"), "").unwrap();
+    std::os::unix::fs::symlink("..", dir.join("sub/up")).unwrap();
+    let walk = format!("(walk-dir \"{}\")", dir.display());
+    let glob = format!("(glob \"{}/**/*.risp\")", dir.display());
+    let (status, out, err) = risp(&["-e", &walk, "-e", &glob], b"");
+    assert_eq!(status, Some(0), "{}", err);
+    // long vectors are printed over several lines
+    let out: Vec<&str> = out.split_whitespace().collect();
+    let d = dir.display();
+    assert_eq!(
+        out.join(" "),
+        format!("[\"{d}/sub\" \"{d}/sub/a.risp\" \"{d}/sub/up\"] [\"{d}/sub/a.risp\"]")
+    );
+}